@@ -0,0 +1,120 @@
+use std::{error::Error, fmt::Display};
+
+use serde::de::DeserializeOwned;
+
+use crate::{ScriptValue, CommandNoArgError};
+
+#[derive(Debug, Clone)]
+pub struct CommandArgTypeError(pub String, pub String, pub String);
+
+impl Display for CommandArgTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the '{}' command expected its '{}' argument to be {}.", self.0, self.1, self.2)
+    }
+}
+
+impl Error for CommandArgTypeError {}
+
+#[derive(Debug, Clone)]
+pub struct CommandArgVariantError(pub String, pub String, pub String, pub Vec<String>);
+
+impl Display for CommandArgVariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "the '{}' command got '{}' for its '{}' argument, which must be one of: {}.",
+            self.0, self.2, self.1, self.3.join(", ")
+        )
+    }
+}
+
+impl Error for CommandArgVariantError {}
+
+/// Positional command arguments, looked up by the names the command declares them with.
+pub struct CommandArgs<'a> {
+    pub command: &'static str,
+    pub names: &'static [&'static str],
+    pub args: &'a [ScriptValue]
+}
+
+impl<'a> CommandArgs<'a> {
+    pub fn new(command: &'static str, names: &'static [&'static str], args: &'a [ScriptValue]) -> Self {
+        Self { command, names, args }
+    }
+
+    /// Gets an argument by name. A `None` value counts as not given.
+    pub fn get(&self, name: &str) -> Option<&ScriptValue> {
+        let index = self.names.iter().position(|el| *el == name)?;
+        match self.args.get(index) {
+            Some(ScriptValue::None) | None => None,
+            Some(value) => Some(value)
+        }
+    }
+
+    pub fn require(&self, name: &'static str) -> Result<&ScriptValue, Box<dyn Error>> {
+        self.get(name).ok_or(Box::new(CommandNoArgError(self.command, name)) as Box<dyn Error>)
+    }
+
+    fn type_error(&self, name: &str, expected: &str) -> Box<dyn Error> {
+        Box::new(CommandArgTypeError(self.command.to_string(), name.to_string(), expected.to_string()))
+    }
+
+    pub fn require_string(&self, name: &'static str) -> Result<String, Box<dyn Error>> {
+        self.require(name)?.clone().try_into().map_err(|_| self.type_error(name, "a string"))
+    }
+
+    pub fn optional_string(&self, name: &'static str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.get(name) {
+            Some(_) => Ok(Some(self.require_string(name)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn require_int(&self, name: &'static str) -> Result<i64, Box<dyn Error>> {
+        self.require(name)?.clone().try_into().map_err(|_| self.type_error(name, "an integer"))
+    }
+
+    pub fn optional_int(&self, name: &'static str) -> Result<Option<i64>, Box<dyn Error>> {
+        match self.get(name) {
+            Some(_) => Ok(Some(self.require_int(name)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn require_bool(&self, name: &'static str) -> Result<bool, Box<dyn Error>> {
+        self.require(name)?.clone().try_into().map_err(|_| self.type_error(name, "a boolean"))
+    }
+
+    pub fn optional_bool(&self, name: &'static str) -> Result<Option<bool>, Box<dyn Error>> {
+        match self.get(name) {
+            Some(_) => Ok(Some(self.require_bool(name)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Requires a string argument that is one of `variants`, ignoring case.
+    pub fn require_enum(&self, name: &'static str, variants: &[&str]) -> Result<String, Box<dyn Error>> {
+        let value = self.require_string(name)?;
+        variants.iter()
+            .find(|el| el.eq_ignore_ascii_case(&value))
+            .map(|el| el.to_string())
+            .ok_or(Box::new(CommandArgVariantError(
+                self.command.to_string(),
+                name.to_string(),
+                value,
+                variants.iter().map(|el| el.to_string()).collect()
+            )) as Box<dyn Error>)
+    }
+
+    /// Deserializes an argument into any type, such as a settings struct or a list of messages.
+    pub fn require_as<T: DeserializeOwned>(&self, name: &'static str, expected: &str) -> Result<T, Box<dyn Error>> {
+        let value = serde_json::to_value(self.require(name)?)?;
+        serde_json::from_value(value).map_err(|_| self.type_error(name, expected))
+    }
+
+    pub fn optional_as<T: DeserializeOwned>(&self, name: &'static str, expected: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.get(name) {
+            Some(_) => Ok(Some(self.require_as(name, expected)?)),
+            None => Ok(None)
+        }
+    }
+}
@@ -123,6 +123,13 @@ Keep every field in that exact order.
     let employee = "Employee".blue();
 
     loop {
+        let tokens = context.agents.take_usage();
+        context.token_budget.used += tokens;
+        if context.token_budget.is_exhausted() {
+            println!("{}", "Token budget exhausted. Stopping.".red());
+            break;
+        }
+
         let thoughts = try_parse_json::<EmployeeThought>(&context.agents.employee.llm, 2, Some(400))?;
         let ParsedResponse { data: thoughts, raw } = thoughts;
 
@@ -140,18 +147,23 @@ Keep every field in that exact order.
         }
 
         let command = plugins.iter()
-            .flat_map(|el| &el.commands)
-            .find(|el| el.name == command_name);
+            .flat_map(|plugin| plugin.commands.iter().map(move |command| (plugin, command)))
+            .find(|(_, command)| command.name == command_name);
+
+        // Each turn starts with the whole output budget.
+        context.output_budget.used = 0;
 
         let mut out = String::new();
         match command {
-            Some(command) => {
+            Some((plugin, command)) => {
                 let rt = Runtime::new().unwrap();
                 rt.block_on(async {
                     run_command(
                         &mut out, 
                         command_name.clone(), 
                         command.box_clone(), 
+                        &plugin.name,
+                        plugin.default_timeout,
                         &mut context, 
                         args
                     ).await
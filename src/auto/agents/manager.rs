@@ -96,6 +96,13 @@ Keep every field in that exact order.
         } = program;
         let mut context = context.lock().unwrap();
 
+        let tokens = context.agents.take_usage();
+        context.token_budget.used += tokens;
+        if context.token_budget.is_exhausted() {
+            println!("{}", "Token budget exhausted. Stopping.".red());
+            break;
+        }
+
         let thoughts = try_parse_json::<ManagerThought>(&context.agents.managers[layer].llm, 2, Some(400))?;
         let ParsedResponse { data: thoughts, raw } = thoughts;
 
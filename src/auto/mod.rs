@@ -5,7 +5,7 @@ use serde_yaml::Value;
 
 use colored::Colorize;
 
-use crate::{LLM, ProgramInfo, Message};
+use crate::{LLM, ProgramInfo, Message, repair_json};
 
 use agents::{employee::run_employee, manager::run_manager};
 
@@ -16,6 +16,8 @@ mod run;
 mod responses;
 mod classify;
 
+pub use run::{dispatch_command, Dispatched};
+
 pub fn run_task_auto(program: &mut ProgramInfo, task: &str) -> Result<String, Box<dyn Error>> {
     let ProgramInfo { 
         context, ..
@@ -118,7 +120,15 @@ pub fn try_parse_yaml<T : DeserializeOwned>(llm: &LLM, tries: usize, max_tokens:
 }
 
 pub fn try_parse_json<T : DeserializeOwned>(llm: &LLM, tries: usize, max_tokens: Option<u16>) -> Result<ParsedResponse<T>, Box<dyn Error>> {
-    try_parse_base(llm, tries, max_tokens, "json", |str| serde_json::from_str(str).map_err(|el| Box::new(el) as Box<dyn Error>))
+    // Malformed JSON gets repaired before it costs another try, unless repair is turned off.
+    try_parse_base(llm, tries, max_tokens, "json", |str| {
+        serde_json::from_str(str)
+            .or_else(|err| match llm.json_repair {
+                true => serde_json::from_str(&repair_json(str)),
+                false => Err(err)
+            })
+            .map_err(|el| Box::new(el) as Box<dyn Error>)
+    })
 }
 
 pub fn try_parse_base<T : DeserializeOwned>(llm: &LLM, tries: usize, max_tokens: Option<u16>, lang: &str, parse: impl Fn(&str) -> Result<T, Box<dyn Error>>) -> Result<ParsedResponse<T>, Box<dyn Error>> {
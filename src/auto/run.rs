@@ -1,23 +1,215 @@
-use std::{sync::{Mutex, Arc}, error::Error};
+use std::{sync::{Mutex, Arc}, error::Error, time::Duration};
 
-use crate::{ScriptValue, ProgramInfo, Command, CommandContext, Expression, GPTRunError};
+use serde_json::json;
+use colored::Colorize;
+use tokio::{select, time::{timeout, sleep}};
 
-pub async fn run_command(
-    out: &mut String,
-    name: String, command: Command, 
-    context: &mut CommandContext, args: Vec<ScriptValue>
-) -> Result<ScriptValue, Box<dyn Error>> {
-    let result = command.run.invoke(context, args.clone()).await?;
+use crate::{ScriptValue, ProgramInfo, Command, CommandContext, Expression, GPTRunError, CommandTimeoutError, ResultFormat, ContentFilteredError, OperationCancelledError, CommandSettings, CommandCache, Budget, collect_chatgpt_usage, validate_command_args, describe_command};
 
-    let args: Vec<Expression> = args.iter().map(|el| el.clone().into()).collect();
-    let expr = Expression::FunctionCall(name.clone(), args);
+/// The longest a failed command waits before it is retried.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
 
+/// Doubles from half a second with each attempt, up to `MAX_RETRY_BACKOFF`.
+fn retry_backoff(attempt: usize) -> Duration {
+    let millis = u32::try_from(attempt.saturating_sub(1)).ok()
+        .and_then(|exponent| 2u64.checked_pow(exponent))
+        .map(|factor| factor.saturating_mul(500))
+        .unwrap_or(u64::MAX);
+
+    Duration::from_millis(millis).min(MAX_RETRY_BACKOFF)
+}
+
+/// Frames a command's result for the model. Framed formats make it easier for the model to tell
+/// where the command output starts and ends.
+pub fn format_command_result(format: ResultFormat, name: &str, args: &[ScriptValue], result: &ScriptValue) -> Result<String, Box<dyn Error>> {
     let json = serde_json::to_string(&result)
         .map_err(|_| GPTRunError("Could not parse ScriptValue as JSON.".to_string()))?;
 
-    let text = format!("Command {:?} returned:\n{}", expr, json);
+    Ok(match format {
+        ResultFormat::Plain => {
+            let args: Vec<Expression> = args.iter().map(|el| el.clone().into()).collect();
+            let expr = Expression::FunctionCall(name.to_string(), args);
+
+            format!("Command {:?} returned:\n{}", expr, json)
+        }
+        ResultFormat::Json => {
+            let framed = json!({
+                "command": name,
+                "args": args,
+                "result": result
+            });
+
+            format!("```json\n{}\n```", serde_json::to_string_pretty(&framed)?)
+        }
+        ResultFormat::Delimited => {
+            format!("<command-result name=\"{name}\">\n{json}\n</command-result>")
+        }
+    })
+}
+
+/// Cuts off output that is too long for the model, saying how much was left out.
+pub fn truncate_output(text: &str, max_length: usize) -> String {
+    let length = text.chars().count();
+    if length <= max_length {
+        return text.to_string();
+    }
+
+    let kept = text.chars().take(max_length).collect::<String>();
+    format!("{kept}\n[Output truncated: showing {max_length} of {length} characters.]")
+}
+
+/// Shows a command's result to the model, framed and cut down to the command's output limit.
+/// Results also share the turn's output budget, so the earlier results in a turn are kept whole
+/// and the later ones are cut down or left out.
+fn push_command_result(
+    out: &mut String, settings: &CommandSettings, output_budget: &mut Budget,
+    name: &str, args: &[ScriptValue], result: &ScriptValue
+) -> Result<(), Box<dyn Error>> {
+    let text = format_command_result(settings.result_format, name, args, result)?;
+    let text = match settings.get_max_output_length(name) {
+        Some(max_length) => truncate_output(&text, max_length),
+        None => text
+    };
+    let text = match output_budget.remaining() {
+        Some(0) => format!("[Output of '{name}' left out: the output budget for this turn is used up.]"),
+        Some(remaining) => truncate_output(&text, remaining),
+        None => text
+    };
+    output_budget.used += text.chars().count();
     out.push_str(&text);
     println!("{}", text);
 
-    Ok(result)
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// What happened when the dispatcher was asked to run a command.
+pub enum Dispatched {
+    /// The command ran, or its result was served from the cache.
+    Finished(ScriptValue),
+    /// The command didn't run, or didn't finish, with why, written for the model.
+    Stopped(String)
+}
+
+/// Runs a command with everything the dispatcher applies to it: argument validation, the recursion guard,
+/// the cache, the command budget, timeouts, retries, cancellation, and usage tracking. Every command runs
+/// through here, whether the agent asked for it or another command (like a pipe step) did.
+/// Errors are returned only when there is no message for the model to act on instead.
+pub async fn dispatch_command(
+    name: &str, command: &Command, plugin: &str, plugin_timeout: Option<Duration>,
+    context: &mut CommandContext, args: &[ScriptValue]
+) -> Result<Dispatched, Box<dyn Error>> {
+    let name = name.to_string();
+
+    // Bad arguments are sent back to the agent with the expected signature, so it can fix the call.
+    let problems = validate_command_args(command, args);
+    if problems.len() > 0 {
+        let text = format!("```json\n{}\n```", serde_json::to_string_pretty(&json!({
+            "error": "invalid arguments",
+            "command": name,
+            "problems": problems,
+            "expected": describe_command(command).trim()
+        }))?);
+        return Ok(Dispatched::Stopped(format!("The '{name}' command was not run, because its arguments did not match its declaration. Fix them and try again.\n{text}")));
+    }
+
+    let depth = context.call_stack.iter().filter(|el| **el == name).count();
+    if depth >= context.command_settings.max_recursion_depth {
+        return Ok(Dispatched::Stopped(format!("The '{name}' command was not run, because it is already running {depth} levels deep. Do not call '{name}' from inside itself; answer directly instead.")));
+    }
+
+    // Pure commands run again with the same arguments are served from the cache, which costs no budget.
+    let cache_key = match command.run.is_pure() {
+        true => CommandCache::key(&name, args),
+        false => None
+    };
+    if let Some(cached) = cache_key.as_ref().and_then(|key| context.command_cache.get(key)) {
+        // The sources are recorded again, so that a cached result is cited like a fresh one.
+        context.citations.extend(cached.citations);
+        return Ok(Dispatched::Finished(cached.value));
+    }
+
+    if context.command_budget.is_exhausted() {
+        return Ok(Dispatched::Stopped(format!("The '{name}' command was not run, because the command budget for this run is used up. Do not run any more commands; conclude with what you have.")));
+    }
+    context.command_budget.used += 1;
+
+    let timeout_duration = context.command_settings.get_timeout(&name, plugin_timeout);
+    let retries = context.command_settings.get_retries(&name);
+
+    let citations_before = context.citations.len();
+    let mut attempt = 0;
+    context.call_stack.push(name.clone());
+    let (operation, cancelled) = context.operations.start(&name);
+    let (result, usage) = loop {
+        let result = select! {
+            result = timeout(timeout_duration, command.run.invoke(context, args.to_vec())) => Some(result),
+            _ = cancelled.cancelled() => None
+        };
+        // A failure to collect usage is held until the call stack and operation are cleaned up.
+        let usage = collect_chatgpt_usage(context).await;
+
+        let result = match result {
+            Some(result) => result
+                .map_err(|_| Box::new(CommandTimeoutError(name.clone(), timeout_duration)) as Box<dyn Error>)
+                .and_then(|el| el),
+            None => Err(Box::new(OperationCancelledError(name.clone(), operation)) as Box<dyn Error>)
+        };
+        let backoff = match result {
+            Err(err) if usage.is_ok() && attempt < retries && !err.is::<ContentFilteredError>() && !err.is::<OperationCancelledError>() => {
+                attempt += 1;
+                let backoff = retry_backoff(attempt);
+                println!("{}: '{}' failed, retrying in {:?} ({} / {}): {}", "Warning".yellow(), name, backoff, attempt, retries, err);
+                backoff
+            }
+            result => break (result, usage)
+        };
+        sleep(backoff).await;
+    };
+    context.call_stack.pop();
+    context.operations.finish(operation);
+    usage?;
+
+    let result = match result {
+        Ok(result) => result,
+        // Refusals are reported to the agent so it can rephrase or move on, instead of ending the run.
+        Err(err) if err.is::<OperationCancelledError>() => {
+            return Ok(Dispatched::Stopped(format!("The '{name}' command was cancelled before it finished.")));
+        }
+        Err(err) if err.is::<ContentFilteredError>() => {
+            return Ok(Dispatched::Stopped(format!("The '{name}' command could not finish: {err} Rephrase the request, or skip it.")));
+        }
+        // With an error template, the model gets guidance it can act on, and the raw error goes to the log.
+        Err(err) => match context.command_settings.format_error(plugin, &name, err.as_ref()) {
+            Some(text) => {
+                println!("{}: '{}' failed: {}", "Error".red(), name, err);
+                return Ok(Dispatched::Stopped(text));
+            }
+            None => return Err(err)
+        }
+    };
+
+    if let Some(key) = cache_key {
+        let citations = context.citations.get(citations_before..).unwrap_or_default().to_vec();
+        context.command_cache.insert(key, result.clone(), citations);
+    }
+
+    Ok(Dispatched::Finished(result))
+}
+
+pub async fn run_command(
+    out: &mut String,
+    name: String, command: Command, plugin: &str, plugin_timeout: Option<Duration>,
+    context: &mut CommandContext, args: Vec<ScriptValue>
+) -> Result<ScriptValue, Box<dyn Error>> {
+    match dispatch_command(&name, &command, plugin, plugin_timeout, context, &args).await? {
+        Dispatched::Finished(result) => {
+            push_command_result(out, &context.command_settings, &mut context.output_budget, &name, &args, &result)?;
+            Ok(result)
+        }
+        Dispatched::Stopped(text) => {
+            out.push_str(&text);
+            println!("{}", text);
+            Ok(ScriptValue::None)
+        }
+    }
+}
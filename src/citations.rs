@@ -0,0 +1,54 @@
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+use crate::{ScriptValue, CommandContext};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Citation {
+    pub title: String,
+    pub url: String,
+    pub fetched_at: String
+}
+
+impl Citation {
+    pub fn new(title: &str, url: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            url: url.to_string(),
+            fetched_at: Utc::now().to_rfc3339()
+        }
+    }
+}
+
+/// Records the citations in the context, and attaches them to a command's output under `citations`.
+pub fn attach_citations(ctx: &mut CommandContext, value: ScriptValue, citations: Vec<Citation>) -> Result<ScriptValue, serde_json::Error> {
+    let citation_values = citations.iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()?;
+    let citation_values: ScriptValue = serde_json::from_value(citation_values.into())?;
+
+    ctx.citations.extend(citations);
+
+    Ok(match value {
+        ScriptValue::Dict(mut dict) => {
+            dict.insert("citations".to_string(), citation_values);
+            ScriptValue::Dict(dict)
+        }
+        value => {
+            ScriptValue::Dict([
+                ("content".to_string(), value),
+                ("citations".to_string(), citation_values)
+            ].into_iter().collect())
+        }
+    })
+}
+
+pub fn collect_citations(ctx: &CommandContext) -> Vec<Citation> {
+    let mut citations: Vec<Citation> = vec![];
+    for citation in &ctx.citations {
+        if !citations.iter().any(|el| el.url == citation.url) {
+            citations.push(citation.clone());
+        }
+    }
+    citations
+}
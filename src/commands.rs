@@ -1,6 +1,139 @@
 use std::error::Error;
 
-use crate::{Plugin, CommandContext};
+use crate::{Plugin, CommandContext, Command, CommandArgument, ScriptValue};
+
+pub fn describe_command(command: &Command) -> String {
+    let arg_names: Vec<_> = command.args.iter()
+        .map(|el| format!("{}: {}", el.name, el.arg_type))
+        .collect();
+    let arg_str = arg_names.join(", ");
+
+    let mut out = String::new();
+    out.push_str(&format!("    {}({arg_str}) -> {}\n", command.name, command.return_type));
+    out.push_str(&format!("        {}\n", command.purpose));
+    /*for CommandArgument { name, description, .. } in &command.args {
+        out.push_str(&format!("            - {}: {}\n", name, description)); 
+    }*/
+    out
+}
+
+/// Splits a type on `separator`, ignoring any inside braces, brackets, or quotes.
+fn split_top_level(arg_type: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut quoted = false;
+    let mut start = 0;
+
+    for (ind, c) in arg_type.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '{' | '[' if !quoted => depth += 1,
+            '}' | ']' if !quoted => depth -= 1,
+            c if c == separator && depth == 0 && !quoted => {
+                parts.push(&arg_type[start..ind]);
+                start = ind + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&arg_type[start..]);
+
+    parts
+}
+
+/// Whether a value fits a declared argument type, like `String`, `Int[]`, `"a" | "b"`, or `{ ... }`.
+/// Types that aren't recognized accept anything, so that an unusual declaration never blocks a command.
+pub fn matches_arg_type(value: &ScriptValue, arg_type: &str) -> bool {
+    let arg_type = arg_type.trim();
+
+    let options = split_top_level(arg_type, '|');
+    if options.len() > 1 {
+        return options.iter().any(|el| matches_arg_type(value, el));
+    }
+
+    if let Some(inner) = arg_type.strip_suffix("[]") {
+        return match value {
+            ScriptValue::List(items) => items.iter().all(|el| matches_arg_type(el, inner)),
+            _ => false
+        };
+    }
+
+    if arg_type.starts_with('{') {
+        return matches!(value, ScriptValue::Dict(_));
+    }
+
+    if let Some(literal) = arg_type.strip_prefix('"').and_then(|el| el.strip_suffix('"')) {
+        return matches!(value, ScriptValue::String(string) if string == literal);
+    }
+
+    match arg_type {
+        "String" => matches!(value, ScriptValue::String(_)),
+        "Int" => matches!(value, ScriptValue::Int(_)),
+        "Float" => matches!(value, ScriptValue::Float(_) | ScriptValue::Int(_)),
+        "bool" | "Bool" => matches!(value, ScriptValue::Bool(_)),
+        "None" => matches!(value, ScriptValue::None),
+        _ => true
+    }
+}
+
+fn is_optional_arg(arg: &CommandArgument) -> bool {
+    arg.optional || matches_arg_type(&ScriptValue::None, &arg.arg_type)
+}
+
+fn describe_value_type(value: &ScriptValue) -> &'static str {
+    match value {
+        ScriptValue::String(_) => "String",
+        ScriptValue::Int(_) => "Int",
+        ScriptValue::Float(_) => "Float",
+        ScriptValue::Bool(_) => "bool",
+        ScriptValue::List(_) => "a list",
+        ScriptValue::Dict(_) => "a dict",
+        ScriptValue::None => "None"
+    }
+}
+
+/// Checks the arguments the model gave a command against the command's declared arguments,
+/// returning one line per problem.
+pub fn validate_command_args(command: &Command, args: &[ScriptValue]) -> Vec<String> {
+    let mut problems = vec![];
+
+    for (ind, arg) in command.args.iter().enumerate() {
+        // A `...rest` argument takes every argument from here on.
+        if arg.name.starts_with("...") {
+            for value in args.iter().skip(ind) {
+                if !matches_arg_type(value, &arg.arg_type) {
+                    problems.push(format!(
+                        "'{}' should all be {}, but one was {}.",
+                        arg.name.trim_start_matches('.'), arg.arg_type, describe_value_type(value)
+                    ));
+                }
+            }
+            return problems;
+        }
+
+        match args.get(ind) {
+            None | Some(ScriptValue::None) if is_optional_arg(arg) => {}
+            None => problems.push(format!("'{}' is required, but was not given.", arg.name)),
+            Some(value) => {
+                if !matches_arg_type(value, &arg.arg_type) {
+                    problems.push(format!(
+                        "'{}' should be {}, but got {}.",
+                        arg.name, arg.arg_type, describe_value_type(value)
+                    ));
+                }
+            }
+        }
+    }
+
+    if args.len() > command.args.len() {
+        problems.push(format!(
+            "expected at most {} arguments, but got {}.",
+            command.args.len(), args.len()
+        ));
+    }
+
+    problems
+}
 
 pub fn generate_commands(plugins: &[Plugin], disabled_commands: &[String]) -> String {
     let mut out = String::new();
@@ -10,16 +143,7 @@ pub fn generate_commands(plugins: &[Plugin], disabled_commands: &[String]) -> St
                 continue;
             }
 
-            let arg_names: Vec<_> = command.args.iter()
-                .map(|el| format!("{}: {}", el.name, el.arg_type))
-                .collect();
-            let arg_str = arg_names.join(", ");
-
-            out.push_str(&format!("    {}({arg_str}) -> {}\n", command.name, command.return_type));
-            out.push_str(&format!("        {}\n", command.purpose));
-            /*for CommandArgument { name, description, .. } in &command.args {
-                out.push_str(&format!("            - {}: {}\n", name, description)); 
-            }*/
+            out.push_str(&describe_command(command));
         }
     }
     out.trim_end().to_string()
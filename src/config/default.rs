@@ -33,5 +33,13 @@ plugins:
         api key: PUT YOUR KEY HERE
     wikipedia: {}
     none: {}
+    system: {}
+    embeddings: {}
+    research: {}
+    text: {}
+    prompts: {}
+    vision: {}
+    pipe: {}
+    duckduckgo: {}
 disabled commands: []
 "#;
\ No newline at end of file
@@ -1,11 +1,11 @@
-use std::{collections::HashMap, error::Error, fmt::Display, process, sync::{Mutex, Arc}};
+use std::{collections::HashMap, error::Error, fmt::Display, process, sync::{Mutex, Arc}, time::Duration};
 
 use colored::Colorize;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 use async_openai::Client as OpenAIClient;
 
-use crate::{CommandContext, EndGoals, LLM, ChatGPT, Plugin, create_browse, create_google, create_filesystem, create_shutdown, create_wolfram, create_chatgpt, create_news, create_wikipedia, create_none, LLMProvider, create_model_chatgpt, Agents, LLMModel, create_model_llama, AgentInfo, MemoryProvider, create_memory_local, MemorySystem};
+use crate::{CommandContext, EndGoals, LLM, ChatGPT, Plugin, create_browse, create_google, create_filesystem, create_shutdown, create_wolfram, create_chatgpt, create_news, create_wikipedia, create_none, create_system, create_embeddings, create_research, create_text, create_prompts, create_vision, create_pipe, create_duckduckgo, create_custom, CustomCommandConfig, LLMProvider, create_model_chatgpt, Agents, LLMModel, create_model_llama, AgentInfo, MemoryProvider, create_memory_local, MemorySystem, ProgressSink, CommandSettings, ResultFormat, Budget, OperationRegistry, CommandCache, CommandOwner, render_error_template};
 
 mod default;
 pub use default::*;
@@ -47,6 +47,76 @@ pub struct AgentLLMs {
     fast: AgentConfig,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandConfig {
+    pub timeout: Option<u64>,
+    /// How many more times to run the command if it fails. Only set this for commands that are safe
+    /// to run twice, since a failed attempt may already have had side effects.
+    #[serde(default)] pub retries: Option<usize>,
+    #[serde(rename = "max output length", default)] pub max_output_length: Option<usize>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandsConfig {
+    #[serde(rename = "default timeout", default = "default_command_timeout")] pub default_timeout: u64,
+    #[serde(default)] pub overrides: HashMap<String, CommandConfig>,
+    #[serde(rename = "result format", default)] pub result_format: ResultFormat,
+    #[serde(rename = "max output length", default)] pub max_output_length: Option<usize>,
+    #[serde(rename = "max recursion depth", default = "default_max_recursion_depth")] pub max_recursion_depth: usize,
+    /// Messages shown to the model when a plugin's command fails, by plugin, with `{command}` and `{error}` placeholders.
+    #[serde(rename = "error templates", default)] pub error_templates: HashMap<String, String>,
+    /// How many seconds the results of pure commands are reused for. Nothing is cached without it.
+    #[serde(rename = "cache ttl", default)] pub cache_ttl: Option<u64>,
+    /// How many characters of command output the model is shown in one turn, across every command run in it.
+    #[serde(rename = "turn output budget", default)] pub turn_output_budget: Option<usize>
+}
+
+impl Default for CommandsConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout: default_command_timeout(),
+            overrides: HashMap::new(),
+            result_format: ResultFormat::default(),
+            max_output_length: None,
+            max_recursion_depth: default_max_recursion_depth(),
+            error_templates: HashMap::new(),
+            cache_ttl: None,
+            turn_output_budget: None
+        }
+    }
+}
+
+fn default_command_timeout() -> u64 {
+    60
+}
+
+fn default_max_recursion_depth() -> usize {
+    2
+}
+
+impl From<CommandsConfig> for CommandSettings {
+    fn from(value: CommandsConfig) -> Self {
+        CommandSettings {
+            default_timeout: Duration::from_secs(value.default_timeout),
+            timeouts: value.overrides.iter()
+                .filter_map(|(name, command)| command.timeout.map(|el| (name.clone(), Duration::from_secs(el))))
+                .collect(),
+            retries: value.overrides.iter()
+                .filter_map(|(name, command)| command.retries.map(|el| (name.clone(), el)))
+                .collect(),
+            max_output_lengths: value.overrides.iter()
+                .filter_map(|(name, command)| command.max_output_length.map(|el| (name.clone(), el)))
+                .collect(),
+            default_max_output_length: value.max_output_length,
+            result_format: value.result_format,
+            max_recursion_depth: value.max_recursion_depth,
+            error_templates: value.error_templates.into_iter()
+                .map(|(plugin, template)| (plugin.to_ascii_lowercase(), template))
+                .collect()
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
@@ -55,7 +125,16 @@ pub struct Config {
     pub personality: String,
     pub agents: AgentLLMs,
     pub plugins: HashMap<String, Value>,
-    #[serde(rename = "disabled commands")] pub disabled_commands: Vec<String>
+    #[serde(rename = "disabled commands")] pub disabled_commands: Vec<String>,
+    #[serde(rename = "max concurrent commands", default = "default_max_concurrent_commands")] pub max_concurrent_commands: usize,
+    #[serde(default)] pub commands: CommandsConfig,
+    #[serde(rename = "token budget", default)] pub token_budget: Option<usize>,
+    #[serde(rename = "command budget", default)] pub command_budget: Option<usize>,
+    #[serde(rename = "custom commands", default)] pub custom_commands: Vec<CustomCommandConfig>
+}
+
+fn default_max_concurrent_commands() -> usize {
+    4
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -81,6 +160,9 @@ pub struct ProgramInfo {
     pub auto_type: AutoType,
     pub plugins: Vec<Plugin>,
     pub context: Arc<Mutex<CommandContext>>,
+    /// The commands in flight. The context stays locked while the agent runs, so front-ends
+    /// list and cancel operations through this instead.
+    pub operations: OperationRegistry,
     pub disabled_commands: Vec<String>
 }
 
@@ -94,7 +176,15 @@ pub fn list_plugins() -> Vec<Plugin> {
         create_chatgpt(),
         create_news(),
         create_wikipedia(),
-        create_none()
+        create_none(),
+        create_system(),
+        create_embeddings(),
+        create_research(),
+        create_text(),
+        create_prompts(),
+        create_vision(),
+        create_pipe(),
+        create_duckduckgo()
     ]
 }
 
@@ -133,13 +223,14 @@ pub fn create_memory_model(agent: HashMap<String, Value>) -> Result<Box<dyn Memo
     Ok(memory_provider.create(model_config.clone())?)
 }
 
-pub fn create_agent(agent: AgentConfig) -> Result<AgentInfo, Box<dyn Error>> {
+pub fn create_agent(agent: AgentConfig, json_repair: bool) -> Result<AgentInfo, Box<dyn Error>> {
     Ok(AgentInfo {
         llm: LLM {
             prompt: vec![],
             message_history: vec![],
             end_prompt: vec![],
-            model: create_llm_model(agent.llm)?
+            model: create_llm_model(agent.llm)?,
+            json_repair
         },
         observations: create_memory_model(agent.memory.clone())?,
         reflections: create_memory_model(agent.memory)?
@@ -147,17 +238,54 @@ pub fn create_agent(agent: AgentConfig) -> Result<AgentInfo, Box<dyn Error>> {
 }
 
 pub fn load_config(config: &str) -> Result<ProgramInfo, Box<dyn Error>> {
-    let config: Config = serde_yaml::from_str(config)?;
+    let mut config: Config = serde_yaml::from_str(config)?;
+    // A plugin listed without any settings (`browse:`) still gets its data, built from the defaults.
+    for plugin_info in config.plugins.values_mut() {
+        if plugin_info.is_null() {
+            *plugin_info = Value::Object(Default::default());
+        }
+    }
+    for template in config.commands.error_templates.values() {
+        render_error_template(template, "", "")?;
+    }
+
+    // The agents parse their own JSON the way the ChatGPT plugin's 'json repair' setting says to.
+    let json_repair = config.plugins.get("chatgpt")
+        .and_then(|el| el.get("json repair"))
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
 
     let mut context = CommandContext {
         auto_type: config.auto_type.clone(),
         command_out: vec![],
+        citations: vec![],
+        plugin_configs: config.plugins.clone(),
+        command_settings: config.commands.clone().into(),
+        token_budget: Budget {
+            limit: config.token_budget,
+            used: 0
+        },
+        command_budget: Budget {
+            limit: config.command_budget,
+            used: 0
+        },
+        output_budget: Budget {
+            limit: config.commands.turn_output_budget,
+            used: 0
+        },
+        progress: ProgressSink::default(),
+        commands: vec![],
+        command_owners: HashMap::new(),
+        call_stack: vec![],
+        operations: OperationRegistry::default(),
+        command_cache: CommandCache::new(config.commands.cache_ttl.map(Duration::from_secs)),
+        max_concurrency: config.max_concurrent_commands.max(1),
         variables: HashMap::new(),
         plugin_data: crate::PluginStore(HashMap::new()),
         agents: Agents {
-            managers: config.agents.managers.iter().map(|el| create_agent(el.clone())).collect::<Result<_, _>>()?,
-            employee: create_agent(config.agents.employee)?,
-            fast: create_agent(config.agents.fast)?
+            managers: config.agents.managers.iter().map(|el| create_agent(el.clone(), json_repair)).collect::<Result<_, _>>()?,
+            employee: create_agent(config.agents.employee, json_repair)?,
+            fast: create_agent(config.agents.fast, json_repair)?
         }
     };
 
@@ -177,20 +305,48 @@ pub fn load_config(config: &str) -> Result<ProgramInfo, Box<dyn Error>> {
     
     for plugin in plugins {
         if let Some(plugin_info) = config.plugins.get(&plugin.name.to_lowercase()) {
-            let data = plugin.cycle.create_data(plugin_info.clone());
-            if let Some(data) = data {
-                context.plugin_data.0.insert(plugin.name.clone(), data);
+            match plugin.cycle.create_data(plugin_info.clone()) {
+                Ok(Some(data)) => {
+                    context.plugin_data.0.insert(plugin.name.clone(), data);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    println!("{}: Could not load the config for plugin \"{}\": {}", "Error".red(), plugin.name, err);
+                    exit = true;
+                }
             }
 
             used_plugins.push(plugin);
         }
     }
+    if exit {
+        process::exit(1);
+    }
+
+    if config.custom_commands.len() > 0 {
+        used_plugins.push(create_custom(config.custom_commands));
+    }
+
+    context.commands = used_plugins.iter()
+        .flat_map(|plugin| plugin.commands.iter())
+        .filter(|command| !config.disabled_commands.contains(&command.name))
+        .map(|command| command.box_clone())
+        .collect();
+    context.command_owners = used_plugins.iter()
+        .flat_map(|plugin| plugin.commands.iter().map(move |command| (command.name.clone(), CommandOwner {
+            plugin: plugin.name.clone(),
+            default_timeout: plugin.default_timeout
+        })))
+        .collect();
+
+    let operations = context.operations.clone();
 
     Ok(ProgramInfo {
         personality: config.personality,
         auto_type: config.auto_type.clone(),
         plugins: used_plugins,
         context: Arc::new(Mutex::new(context)),
+        operations,
         disabled_commands: config.disabled_commands
     })
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String)
+}
+
+impl DiffLine {
+    pub fn to_marked(&self) -> String {
+        match self {
+            DiffLine::Same(line) => format!("  {line}"),
+            DiffLine::Removed(line) => format!("- {line}"),
+            DiffLine::Added(line) => format!("+ {line}")
+        }
+    }
+}
+
+/// The most cells the LCS table may have. Past this, the changed middle is shown as removed, then added.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// A line diff based on the longest common subsequence of the two texts' lines. Lines the texts
+/// start or end with in common are matched up first, so only the changed middle needs the table.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+
+    let mut diff: Vec<DiffLine> = old[..prefix].iter().map(|el| DiffLine::Same(el.to_string())).collect();
+    diff.extend(diff_middle(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]));
+    diff.extend(old[old.len() - suffix..].iter().map(|el| DiffLine::Same(el.to_string())));
+
+    diff
+}
+
+fn diff_middle(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    if (old.len() + 1).saturating_mul(new.len() + 1) > MAX_LCS_CELLS {
+        return old.iter().map(|el| DiffLine::Removed(el.to_string()))
+            .chain(new.iter().map(|el| DiffLine::Added(el.to_string())))
+            .collect();
+    }
+
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|el| DiffLine::Removed(el.to_string())));
+    diff.extend(new[j..].iter().map(|el| DiffLine::Added(el.to_string())));
+
+    diff
+}
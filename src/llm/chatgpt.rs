@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{error::Error, sync::atomic::{AtomicUsize, Ordering}};
 
 use async_openai::{Client, types::{CreateChatCompletionResponse, CreateChatCompletionRequest, ChatCompletionRequestMessage, Role, CreateEmbeddingRequest, EmbeddingInput}, error::OpenAIError, Chat};
 use async_trait::async_trait;
@@ -11,7 +11,8 @@ use crate::{LLMProvider, Message, LLMModel};
 pub struct ChatGPT {
     pub model: String,
     pub embedding_model: String,
-    pub client: Client
+    pub client: Client,
+    pub usage: AtomicUsize
 }
 
 #[async_trait]
@@ -34,6 +35,9 @@ impl LLMModel for ChatGPT {
             .create(request.clone())
             .await?;
 
+        let tokens = response.usage.as_ref().map(|el| el.total_tokens as usize).unwrap_or(0);
+        self.usage.fetch_add(tokens, Ordering::Relaxed);
+
         Ok(response.choices[0].message.content.clone())
     }
     
@@ -43,6 +47,7 @@ impl LLMModel for ChatGPT {
             user: None,
             input: EmbeddingInput::String(text.to_string())
         }).await?;
+        self.usage.fetch_add(embeddings.usage.total_tokens as usize, Ordering::Relaxed);
     
         Ok(embeddings.data[0].embedding.clone())
     }
@@ -56,6 +61,10 @@ impl LLMModel for ChatGPT {
         let tokens = get_chat_completion_max_tokens(&self.model, &messages)?;
         Ok(tokens)
     }
+
+    fn take_usage(&self) -> usize {
+        self.usage.swap(0, Ordering::Relaxed)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -83,7 +92,8 @@ impl LLMProvider for ChatGPTProvider {
         Ok(Box::new(ChatGPT {
             model: config.model.unwrap_or("gpt-3.5-turbo".to_string()),
             embedding_model: config.embedding_model.unwrap_or("text-embedding-ada-002".to_string()),
-            client: Client::new().with_api_key(config.api_key.clone())
+            client: Client::new().with_api_key(config.api_key.clone()),
+            usage: AtomicUsize::new(0)
         }))
     }
 }
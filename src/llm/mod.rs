@@ -89,6 +89,11 @@ pub trait LLMModel : Send + Sync {
     async fn get_base_embed(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>>;
     fn get_tokens_remaining(&self, text: &[Message]) -> Result<usize, Box<dyn Error>>;
 
+    /// Takes the tokens used since the last call. Models that don't report usage have used none.
+    fn take_usage(&self) -> usize {
+        0
+    }
+
     fn get_response_sync(&self, messages: &[Message], max_tokens: Option<u16>, temperature: Option<f32>) -> Result<String, Box<dyn Error>> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
@@ -114,7 +119,9 @@ pub struct LLM {
     pub prompt: Vec<Message>,
     pub end_prompt: Vec<Message>,
     pub message_history: Vec<Message>,
-    pub model: Box<dyn LLMModel>
+    pub model: Box<dyn LLMModel>,
+    /// Whether malformed JSON from the model is repaired locally before it costs another try.
+    pub json_repair: bool
 }
 
 impl LLM {
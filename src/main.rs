@@ -9,7 +9,14 @@ use async_openai::{
 mod plugin;
 mod plugins;
 mod commands;
+mod args;
 mod chunk;
+mod citations;
+mod diff;
+mod progress;
+mod operations;
+mod memoize;
+mod template;
 mod llm;
 mod config;
 mod runner;
@@ -19,7 +26,14 @@ mod auto;
 pub use plugin::*;
 pub use plugins::*;
 pub use commands::*;
+pub use args::*;
 pub use chunk::*;
+pub use citations::*;
+pub use diff::*;
+pub use progress::*;
+pub use operations::*;
+pub use memoize::*;
+pub use template::*;
 pub use llm::*;
 pub use config::*;
 pub use runner::*;
@@ -117,13 +131,6 @@ fn main() -> Result<(), Box<dyn Error>> {
             }
             println!(")");
         }
-
-        // OH NO OH NO OH NO
-        let data = plugin.cycle.create_data(true.into());
-        if let Some(data) = data {
-            let mut context = program.context.lock().unwrap();
-            context.plugin_data.0.insert(plugin.name.clone(), data);
-        }
     }
 
     if exit_dependency_error {
@@ -144,6 +151,19 @@ fn main() -> Result<(), Box<dyn Error>> {
 
                 println!();
 
+                // Operator commands are handled here and never reach the model or the transcript.
+                if let Some(rest) = input.trim().strip_prefix("/set-key ") {
+                    match rest.split_once(' ') {
+                        Some((plugin, key)) => match rotate_api_key(&program.context, plugin, key.trim()) {
+                            Ok(()) => println!("{}: Replaced the API key for {}.", "Operator".blue(), plugin),
+                            Err(err) => println!("{}: Could not replace the API key for {}: {}", "Error".red(), plugin, err)
+                        },
+                        None => println!("{}: Usage: /set-key <plugin> <key>", "Error".red())
+                    }
+                    println!();
+                    continue;
+                }
+
                 let response = run_assistant_auto(&mut program, &messages, &input)?;
 
                 messages.push(Message::User(input));
@@ -0,0 +1,55 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use crate::{ScriptValue, Citation};
+
+/// A command's result, with the citations it recorded while running.
+#[derive(Clone)]
+pub struct CachedResult {
+    pub stored_at: Instant,
+    pub value: ScriptValue,
+    pub citations: Vec<Citation>
+}
+
+/// Results of pure commands, so that the dispatcher can serve a repeated call without running it again.
+#[derive(Default)]
+pub struct CommandCache {
+    /// How long a result is served for. Without one, nothing is cached.
+    pub ttl: Option<Duration>,
+    entries: HashMap<String, CachedResult>
+}
+
+impl CommandCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new()
+        }
+    }
+
+    /// Dicts serialize with sorted keys, so the same arguments always give the same key.
+    pub fn key(command: &str, args: &[ScriptValue]) -> Option<String> {
+        let args = serde_json::to_string(args).ok()?;
+        Some(format!("{command}{args}"))
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<CachedResult> {
+        let ttl = self.ttl?;
+        self.entries.retain(|_, el| el.stored_at.elapsed() < ttl);
+
+        self.entries.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: String, value: ScriptValue, citations: Vec<Citation>) {
+        if self.ttl.is_some() {
+            self.entries.insert(key, CachedResult {
+                stored_at: Instant::now(),
+                value,
+                citations
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
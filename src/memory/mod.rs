@@ -118,4 +118,14 @@ pub fn compare_embeddings(a: &Vec<f32>, b: &Vec<f32>) -> f32 {
     let norm_b = b.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
     let min_length = min(a.len(), b.len()) as f32;
     dot_product / (norm_a * norm_b * min_length)
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot_product = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>();
+    let norm_a = a.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    if norm_a == 0. || norm_b == 0. {
+        return 0.;
+    }
+    dot_product / (norm_a * norm_b)
 }
\ No newline at end of file
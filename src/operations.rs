@@ -0,0 +1,84 @@
+use std::{error::Error, fmt::Display, sync::{Arc, Mutex}};
+
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct OperationCancelledError(pub String, pub usize);
+
+impl Display for OperationCancelledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the '{}' command (operation {}) was cancelled.", self.0, self.1)
+    }
+}
+
+impl Error for OperationCancelledError {}
+
+/// A command that is running right now.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OperationInfo {
+    pub id: usize,
+    pub command: String,
+    pub started_at: String
+}
+
+struct Operation {
+    info: OperationInfo,
+    token: CancellationToken
+}
+
+#[derive(Default)]
+struct OperationList {
+    next_id: usize,
+    operations: Vec<Operation>
+}
+
+/// The commands in flight. It's shared, so a front-end can keep a clone and cancel operations from another task.
+#[derive(Clone, Default)]
+pub struct OperationRegistry {
+    inner: Arc<Mutex<OperationList>>
+}
+
+impl OperationRegistry {
+    /// Registers a command as running, returning its id and the token that cancels it.
+    pub fn start(&self, command: &str) -> (usize, CancellationToken) {
+        let mut list = self.inner.lock().unwrap();
+        list.next_id += 1;
+
+        let id = list.next_id;
+        let token = CancellationToken::new();
+        list.operations.push(Operation {
+            info: OperationInfo {
+                id,
+                command: command.to_string(),
+                started_at: Utc::now().to_rfc3339()
+            },
+            token: token.clone()
+        });
+
+        (id, token)
+    }
+
+    pub fn finish(&self, id: usize) {
+        self.inner.lock().unwrap().operations.retain(|el| el.info.id != id);
+    }
+
+    pub fn list(&self) -> Vec<OperationInfo> {
+        self.inner.lock().unwrap().operations.iter()
+            .map(|el| el.info.clone())
+            .collect()
+    }
+
+    /// Cancels an operation, returning whether there was one with that id.
+    pub fn cancel(&self, id: usize) -> bool {
+        let list = self.inner.lock().unwrap();
+        match list.operations.iter().find(|el| el.info.id == id) {
+            Some(operation) => {
+                operation.token.cancel();
+                true
+            }
+            None => false
+        }
+    }
+}
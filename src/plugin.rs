@@ -1,9 +1,9 @@
-use std::{collections::HashMap, error::Error, fmt::Display, future::Future, pin::Pin, any::Any};
+use std::{collections::HashMap, error::Error, fmt::Display, future::Future, pin::Pin, any::Any, time::Duration};
 
 use async_openai::{Client as OpenAIClient, types::ChatCompletionRequestMessage};
 use async_trait::async_trait;
 use reqwest::Client;
-use serde::{Serialize, de::DeserializeOwned, __private::de};
+use serde::{Serialize, Deserialize, de::DeserializeOwned, __private::de};
 use serde_json::Value;
 use tokenizers::Tokenizer;
 
@@ -29,7 +29,7 @@ impl<'a> Display for CommandNoArgError<'a> {
 
 impl<'a> Error for CommandNoArgError<'a> {}
 
-use crate::{LLM, ScriptValue, MemorySystem, AutoType};
+use crate::{LLM, ScriptValue, MemorySystem, AutoType, Citation, ProgressSink, OperationRegistry, CommandCache, MissingTemplateVariablesError, render_template};
 
 #[async_trait]
 pub trait PluginData: Any + Send + Sync {
@@ -61,12 +61,73 @@ pub struct Agents {
     pub fast: AgentInfo
 }
 
+impl Agents {
+    /// Takes the tokens the agents' own models have used since the last call.
+    pub fn take_usage(&self) -> usize {
+        self.managers.iter()
+            .chain([ &self.employee, &self.fast ])
+            .map(|el| el.llm.model.take_usage())
+            .sum()
+    }
+}
+
 pub struct CommandContext {
     pub auto_type: AutoType,
     pub plugin_data: PluginStore,
     pub agents: Agents,
     pub variables: HashMap<String, ScriptValue>,
-    pub command_out: Vec<String>
+    pub command_out: Vec<String>,
+    pub citations: Vec<Citation>,
+    pub plugin_configs: HashMap<String, Value>,
+    pub command_settings: CommandSettings,
+    pub token_budget: Budget,
+    pub command_budget: Budget,
+    /// How many characters of command output the model may be shown in a turn.
+    pub output_budget: Budget,
+    pub progress: ProgressSink,
+    /// Every enabled command, so that commands can look up (or plan with) the others.
+    pub commands: Vec<Command>,
+    /// The plugin each command belongs to, so that commands run by other commands are dispatched like the agent's.
+    pub command_owners: HashMap<String, CommandOwner>,
+    /// The commands currently running through the dispatcher, outermost first.
+    pub call_stack: Vec<String>,
+    pub operations: OperationRegistry,
+    pub command_cache: CommandCache,
+    /// How many fetches or model requests a command may have running at once. Commands are
+    /// dispatched one at a time, so this is where a burst of work actually runs concurrently.
+    pub max_concurrency: usize
+}
+
+impl CommandContext {
+    /// Caps the concurrency a command asks for at the configured limit.
+    pub fn limit_concurrency(&self, requested: usize) -> usize {
+        requested.min(self.max_concurrency).max(1)
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandOwner {
+    pub plugin: String,
+    pub default_timeout: Option<Duration>
+}
+
+/// A cap on something used up over a run, like tokens or commands.
+pub struct Budget {
+    pub limit: Option<usize>,
+    pub used: usize
+}
+
+impl Budget {
+    pub fn is_exhausted(&self) -> bool {
+        match self.limit {
+            Some(limit) => self.used >= limit,
+            None => false
+        }
+    }
+
+    pub fn remaining(&self) -> Option<usize> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
 }
 
 
@@ -86,6 +147,13 @@ impl PluginStore {
         let plugin = plugin.to_string();
         let error = NoPluginDataError(plugin.clone());
         self.0.get_mut(&plugin).ok_or(Box::new(error))
+    }
+
+    /// A plugin's data as its own type, for plugins that need more from each other than `invoke`
+    /// can pass as JSON, like a response body to parse.
+    pub fn get_data_as<T: PluginData>(&mut self, plugin: &str) -> Result<&mut T, Box<dyn Error>> {
+        let data: &mut dyn Any = self.get_data(plugin)?.as_mut();
+        data.downcast_mut::<T>().ok_or(Box::new(NoPluginDataError(plugin.to_string())) as Box<dyn Error>)
     }   
 }
 
@@ -103,12 +171,54 @@ pub trait CommandImpl : Send + Sync {
     async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>>;
 
     fn box_clone(&self) -> Box<dyn CommandImpl>;
+
+    /// Whether the command gives the same result for the same arguments and has no side effects,
+    /// so that the dispatcher may serve its result from the cache. Citations are the exception:
+    /// they are stored with the result and recorded again on a hit. Commands that may ask a model
+    /// anything, like to condense or re-rank, are not pure.
+    fn is_pure(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownConfigFieldsError(pub Vec<String>);
+
+impl Display for UnknownConfigFieldsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown config field(s): {}", self.0.join(", "))
+    }
+}
+
+impl Error for UnknownConfigFieldsError {}
+
+/// Checks that a plugin's config deserializes, and that it has no unknown (likely misnamed) fields.
+pub fn check_config<T : Serialize + DeserializeOwned>(value: Value) -> Result<(), Box<dyn Error>> {
+    let config: T = serde_json::from_value(value.clone())?;
+    let known = serde_json::to_value(config)?;
+
+    if let (Value::Object(value), Value::Object(known)) = (value, known) {
+        let unknown = value.keys()
+            .filter(|key| !known.contains_key(*key))
+            .map(|key| format!("\"{key}\""))
+            .collect::<Vec<_>>();
+
+        if unknown.len() > 0 {
+            return Err(Box::new(UnknownConfigFieldsError(unknown)));
+        }
+    }
+
+    Ok(())
 }
 
 #[async_trait]
 pub trait PluginCycle : Send + Sync {
     async fn create_context(&self, context: &mut CommandContext, previous_prompt: Option<&str>) -> Result<Option<String>, Box<dyn Error>>;
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>>;
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>>;
+
+    fn validate_config(&self, _: Value) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
 }
 
 pub struct EmptyCycle;
@@ -119,8 +229,8 @@ impl PluginCycle for EmptyCycle {
         Ok(None)
     }
 
-    fn create_data(&self, _: Value) -> Option<Box<dyn PluginData>> {
-        None
+    fn create_data(&self, _: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        Ok(None)
     }
 }
 
@@ -128,7 +238,9 @@ impl PluginCycle for EmptyCycle {
 pub struct CommandArgument {
     pub name: String,
     pub description: String,
-    pub arg_type: String
+    pub arg_type: String,
+    /// Whether the argument can be left out of a call.
+    pub optional: bool
 }
 
 impl CommandArgument {
@@ -136,7 +248,15 @@ impl CommandArgument {
         Self {
             name: name.to_string(),
             description: description.to_string(),
-            arg_type: arg_type.to_string()
+            arg_type: arg_type.to_string(),
+            optional: false
+        }
+    }
+
+    pub fn optional(name: &str, description: &str, arg_type: &str) -> Self {
+        Self {
+            optional: true,
+            ..Self::new(name, description, arg_type)
         }
     }
 }
@@ -165,9 +285,63 @@ pub struct Plugin {
     pub name: String,
     pub cycle: Box<dyn PluginCycle>,
     pub dependencies: Vec<String>,
+    pub default_timeout: Option<Duration>,
     pub commands: Vec<Command>
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum ResultFormat {
+    #[serde(rename = "plain")] #[default] Plain,
+    #[serde(rename = "json")] Json,
+    #[serde(rename = "delimited")] Delimited
+}
+
+pub struct CommandSettings {
+    pub default_timeout: Duration,
+    pub timeouts: HashMap<String, Duration>,
+    pub retries: HashMap<String, usize>,
+    pub default_max_output_length: Option<usize>,
+    pub max_output_lengths: HashMap<String, usize>,
+    pub result_format: ResultFormat,
+    /// How many times a command may be running inside itself, to stop commands that call each other from looping forever.
+    pub max_recursion_depth: usize,
+    pub error_templates: HashMap<String, String>
+}
+
+/// Fills in an error template's `{command}` and `{error}` placeholders.
+pub fn render_error_template(template: &str, command: &str, error: &str) -> Result<String, MissingTemplateVariablesError> {
+    render_template(template, &HashMap::from_iter([
+        ("command".to_string(), command.to_string()),
+        ("error".to_string(), error.to_string())
+    ]))
+}
+
+impl CommandSettings {
+    /// A command's own timeout takes priority, then its plugin's, then the global default.
+    pub fn get_timeout(&self, command: &str, plugin_timeout: Option<Duration>) -> Duration {
+        self.timeouts.get(command)
+            .cloned()
+            .or(plugin_timeout)
+            .unwrap_or(self.default_timeout)
+    }
+
+    /// Commands are never retried unless configured to be, since retrying isn't safe for every command.
+    pub fn get_retries(&self, command: &str) -> usize {
+        self.retries.get(command).cloned().unwrap_or(0)
+    }
+
+    /// How many characters of a command's output the model is shown, if limited.
+    pub fn get_max_output_length(&self, command: &str) -> Option<usize> {
+        self.max_output_lengths.get(command).cloned().or(self.default_max_output_length)
+    }
+
+    /// The model-facing message for a failed command, if its plugin has an error template.
+    pub fn format_error(&self, plugin: &str, command: &str, error: &dyn Error) -> Option<String> {
+        let template = self.error_templates.get(&plugin.to_ascii_lowercase())?;
+        render_error_template(template, command, &error.to_string()).ok()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NotFoundError(pub String);
 
@@ -177,4 +351,15 @@ impl Display for NotFoundError {
     }
 }
 
-impl Error for NotFoundError {}
\ No newline at end of file
+impl Error for NotFoundError {}
+
+#[derive(Debug, Clone)]
+pub struct CommandTimeoutError(pub String, pub Duration);
+
+impl Display for CommandTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the '{}' command timed out after {} seconds.", self.0, self.1.as_secs())
+    }
+}
+
+impl Error for CommandTimeoutError {}
\ No newline at end of file
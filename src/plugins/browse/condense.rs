@@ -0,0 +1,39 @@
+use std::error::Error;
+
+use crate::{CommandContext, ask_chatgpt_ephemeral, invoke, report_progress};
+
+use super::chunk_text;
+
+/// How much text goes into each summary request.
+const SUMMARY_CHUNK_SIZE: usize = 11000;
+
+const CONDENSE_PROMPT: &str = r#"You condense web pages. Summarize the page text the user sends you, keeping every important fact, number, and name, in at most a few paragraphs.
+
+Respond with only the summary."#;
+
+/// Summarizes text longer than the Browse plugin's `summarize over` setting with ChatGPT, instead of cutting it off.
+/// Returns `None` when the setting is off, or the text is short enough to return as-is.
+pub async fn condense_long_text(ctx: &mut CommandContext, command: &str, text: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let threshold = match invoke::<Option<usize>>(browse_info, "get summarize over", true).await? {
+        Some(threshold) => threshold,
+        None => return Ok(None)
+    };
+
+    let length = text.chars().count();
+    if length <= threshold {
+        return Ok(None);
+    }
+
+    let chunks = chunk_text(text, SUMMARY_CHUNK_SIZE);
+    let mut summaries = vec![];
+    for (ind, chunk) in chunks.iter().enumerate() {
+        report_progress(ctx, command, "Summarizing Long Page", Some((ind + 1, chunks.len())));
+        summaries.push(ask_chatgpt_ephemeral(ctx, CONDENSE_PROMPT, chunk).await?.trim().to_string());
+    }
+
+    Ok(Some(format!(
+        "[Summarized: the page was {length} characters long, over the {threshold} character limit.]\n\n{}",
+        summaries.join("\n\n")
+    )))
+}
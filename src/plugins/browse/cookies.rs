@@ -0,0 +1,48 @@
+use std::{error::Error, sync::RwLock};
+
+use async_trait::async_trait;
+use reqwest::{Url, cookie::{CookieStore, Jar}, header::HeaderValue};
+
+use crate::{CommandContext, CommandImpl, ScriptValue, invoke};
+
+/// A cookie jar that can be emptied. reqwest's `Jar` can't be, and the client keeps hold of whichever store it was built with.
+#[derive(Default)]
+pub struct ClearableJar {
+    jar: RwLock<Jar>
+}
+
+impl ClearableJar {
+    pub fn clear(&self) {
+        *self.jar.write().unwrap() = Jar::default();
+    }
+}
+
+impl CookieStore for ClearableJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        self.jar.read().unwrap().set_cookies(cookie_headers, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.jar.read().unwrap().cookies(url)
+    }
+}
+
+pub async fn clear_browse_cache(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    invoke::<bool>(browse_info, "clear cache", true).await?;
+
+    Ok("Cleared the Browse plugin's stored cookies.".to_string().into())
+}
+
+pub struct ClearBrowseCache;
+
+#[async_trait]
+impl CommandImpl for ClearBrowseCache {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        clear_browse_cache(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -1,8 +1,20 @@
-use std::{error::Error, collections::HashMap};
+use std::{error::Error, collections::HashMap, fmt::Display};
 
-use reqwest::Client;
+use reqwest::{Client, Url};
 use select::{document::Document, predicate::{Name, Or}};
 use serde::{Serialize, Deserialize};
+use scraper::{Html, Selector};
+
+#[derive(Debug, Clone)]
+pub struct InvalidSelectorError(pub String, pub String);
+
+impl Display for InvalidSelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the selector \"{}\" for the field '{}' is not a valid CSS selector.", self.1, self.0)
+    }
+}
+
+impl Error for InvalidSelectorError {}
 
 pub fn extract_text_from_html(html: &str) -> String {
     let mut text = String::new();
@@ -18,4 +30,136 @@ pub fn extract_text_from_html(html: &str) -> String {
         .trim()
         .replace(|c: char| !c.is_ascii(), "")
         .to_string()
-}
\ No newline at end of file
+}
+
+pub fn extract_title_from_html(html: &str) -> Option<String> {
+    let document = Document::from(html);
+
+    document.find(Name("title"))
+        .next()
+        .map(|el| el.text().trim().to_string())
+        .filter(|el| !el.is_empty())
+}
+
+pub fn extract_links_from_html(html: &str, base: &Url, same_host: bool) -> Vec<String> {
+    let mut links: Vec<String> = vec![];
+
+    let document = Document::from(html);
+
+    for a in document.find(Name("a")) {
+        let href = match a.attr("href") {
+            Some(href) => href,
+            None => continue
+        };
+
+        let mut link = match base.join(href.trim()) {
+            Ok(link) => link,
+            Err(_) => continue
+        };
+        link.set_fragment(None);
+
+        if link.scheme() != "http" && link.scheme() != "https" {
+            continue;
+        }
+
+        if same_host && link.host_str() != base.host_str() {
+            continue;
+        }
+
+        let link = link.to_string();
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+
+    links
+}
+
+/// Extracts the text of the first element matching each field's CSS selector, or `None` if nothing matches.
+pub fn extract_selectors_from_html(html: &str, selectors: &HashMap<String, String>) -> Result<HashMap<String, Option<String>>, InvalidSelectorError> {
+    let document = Html::parse_document(html);
+
+    let mut fields = HashMap::new();
+    for (field, selector) in selectors {
+        let parsed = Selector::parse(selector)
+            .map_err(|_| InvalidSelectorError(field.clone(), selector.clone()))?;
+
+        let text = document.select(&parsed)
+            .next()
+            .map(|el| el.text().collect::<Vec<_>>().join(" ").trim().to_string());
+
+        fields.insert(field.clone(), text);
+    }
+
+    Ok(fields)
+}
+/// Gets the HTML of the page's main content, falling back to the whole body.
+pub fn extract_main_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+
+    for selector in [ "main", "article", "[role=main]", "body" ] {
+        let parsed = Selector::parse(selector).unwrap();
+        if let Some(element) = document.select(&parsed).next() {
+            return element.inner_html();
+        }
+    }
+
+    html.to_string()
+}
+
+/// Converts the page's main content into Markdown, cut off after `max_length` characters.
+pub fn extract_markdown_from_html(html: &str, max_length: usize) -> String {
+    let markdown = html2md::parse_html(&extract_main_html(html));
+    let markdown = markdown.trim();
+
+    match markdown.char_indices().nth(max_length) {
+        Some((end, _)) => format!("{}\n\n...", &markdown[..end]),
+        None => markdown.to_string()
+    }
+}
+
+/// Gets the text of each paragraph, list item, and heading, one per line, so that pages can be diffed by line.
+pub fn extract_paragraphs_from_html(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("h1, h2, h3, h4, h5, h6, p, li").unwrap();
+
+    document.select(&selector)
+        .map(|el| el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|el| !el.is_empty())
+        .collect()
+}
+
+/// A run of text on a page, under the heading it appeared after.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PageSection {
+    pub heading: Option<String>,
+    pub text: String
+}
+
+/// Splits the page's main content into sections, starting a new one at each heading.
+pub fn extract_sections_from_html(html: &str) -> Vec<PageSection> {
+    let document = Html::parse_fragment(&extract_main_html(html));
+    let selector = Selector::parse("h1, h2, h3, h4, h5, h6, p, li").unwrap();
+
+    let mut sections = vec![ PageSection { heading: None, text: String::new() } ];
+    for element in document.select(&selector) {
+        let text = element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+
+        if element.value().name().starts_with('h') {
+            sections.push(PageSection { heading: Some(text), text: String::new() });
+        } else {
+            let section = sections.last_mut().unwrap();
+            if !section.text.is_empty() {
+                section.text.push('\n');
+            }
+            section.text.push_str(&text);
+        }
+    }
+
+    sections.into_iter()
+        .filter(|el| !el.text.is_empty())
+        .collect()
+}
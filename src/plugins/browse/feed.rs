@@ -0,0 +1,95 @@
+use std::{error::Error, collections::HashMap, fmt::Display};
+
+use async_trait::async_trait;
+use feed_rs::parser;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{BrowseRequest, extract_text_from_html, filter_injections};
+
+#[derive(Debug, Clone)]
+pub struct FeedParseError(pub String, pub String);
+
+impl Display for FeedParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the page at '{}' is not a valid RSS or Atom feed. ({})", self.0, self.1)
+    }
+}
+
+impl Error for FeedParseError {}
+
+fn optional(value: Option<String>) -> ScriptValue {
+    value.map(|el| el.into()).unwrap_or(ScriptValue::None)
+}
+
+pub async fn browse_feed(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("browse_feed", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let max_feed_items = invoke::<usize>(browse_info, "get max feed items", true).await?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let feed = parser::parse(body.as_bytes())
+        .map_err(|err| FeedParseError(url.clone(), err.to_string()))?;
+
+    let entries = feed.entries.into_iter()
+        .take(max_feed_items)
+        .map(|entry| {
+            // Summaries are often HTML, so they are reduced to text like browsed pages are.
+            let summary = entry.summary.map(|el| el.content)
+                .or(entry.content.and_then(|el| el.body))
+                .map(|el| {
+                    let text = extract_text_from_html(&format!("<p>{el}</p>"));
+                    if text.is_empty() { el } else { text }
+                });
+
+            (
+                entry.title.map(|el| el.content),
+                entry.links.first().map(|el| el.href.clone()),
+                entry.published.or(entry.updated).map(|el| el.to_rfc3339()),
+                summary
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Titles and summaries are written by the feed's author, so they go through the injection filter.
+    let texts = entries.iter()
+        .flat_map(|(title, _, _, summary)| [ title.clone(), summary.clone() ])
+        .flatten()
+        .collect::<Vec<_>>();
+    let mut texts = filter_injections(ctx, texts).await?.into_iter();
+
+    let items = entries.into_iter()
+        .map(|(title, link, published, summary)| {
+            let title = title.and_then(|_| texts.next());
+            let summary = summary.and_then(|_| texts.next());
+
+            ScriptValue::Dict(HashMap::from_iter([
+                ("title".to_string(), optional(title)),
+                ("link".to_string(), optional(link)),
+                ("published".to_string(), optional(published)),
+                ("summary".to_string(), optional(summary))
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(items.into())
+}
+
+pub struct BrowseFeed;
+
+#[async_trait]
+impl CommandImpl for BrowseFeed {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_feed(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,112 @@
+use std::{error::Error, collections::HashMap};
+
+use async_trait::async_trait;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{BrowseRequest, extract_title_from_html, filter_injections};
+
+/// The tags each field is read from, in order of preference.
+const META_FIELDS: &[(&str, &[&str])] = &[
+    ("title", &[ "og:title", "twitter:title" ]),
+    ("description", &[ "og:description", "twitter:description", "description" ]),
+    ("image", &[ "og:image", "og:image:url", "twitter:image", "twitter:image:src" ]),
+    ("site_name", &[ "og:site_name", "application-name" ]),
+    ("published_time", &[ "article:published_time", "og:published_time", "datePublished", "date" ])
+];
+
+/// Every `<meta>` tag's content, keyed by its lowercased `property`, `name`, or `itemprop`.
+/// The first tag with a given key wins.
+pub fn extract_meta_tags(html: &str) -> HashMap<String, String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("meta[content]").unwrap();
+
+    let mut tags = HashMap::new();
+    for element in document.select(&selector) {
+        let attrs = element.value();
+        let key = attrs.attr("property")
+            .or(attrs.attr("name"))
+            .or(attrs.attr("itemprop"));
+        let (Some(key), Some(content)) = (key, attrs.attr("content")) else {
+            continue;
+        };
+
+        let content = content.trim();
+        if !content.is_empty() {
+            tags.entry(key.trim().to_lowercase()).or_insert(content.to_string());
+        }
+    }
+
+    tags
+}
+
+pub fn extract_page_metadata(html: &str, base: &Url) -> HashMap<String, Option<String>> {
+    let tags = extract_meta_tags(html);
+
+    let mut metadata: HashMap<String, Option<String>> = META_FIELDS.iter()
+        .map(|(field, keys)| {
+            let value = keys.iter().find_map(|key| tags.get(&key.to_lowercase()).cloned());
+            (field.to_string(), value)
+        })
+        .collect();
+
+    // Pages without OpenGraph tags still have a <title>.
+    if let Some(title @ None) = metadata.get_mut("title") {
+        *title = extract_title_from_html(html);
+    }
+
+    // Images are often given relative to the page.
+    if let Some(Some(image)) = metadata.get_mut("image") {
+        if let Ok(url) = base.join(image) {
+            *image = url.to_string();
+        }
+    }
+
+    metadata
+}
+
+pub async fn page_metadata(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("page_metadata", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+    let base = Url::parse(&url)?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let (fields, values): (Vec<_>, Vec<_>) = extract_page_metadata(&body, &base)
+        .into_iter()
+        .filter_map(|(field, value)| Some((field, value?)))
+        .unzip();
+    let values = filter_injections(ctx, values).await?;
+
+    let mut metadata: HashMap<String, ScriptValue> = META_FIELDS.iter()
+        .map(|(field, _)| (field.to_string(), ScriptValue::None))
+        .chain(fields.into_iter().zip(values).map(|(field, value)| (field, value.into())))
+        .collect();
+    metadata.insert("url".to_string(), url.into());
+
+    Ok(ScriptValue::Dict(metadata))
+}
+
+pub struct PageMetadata;
+
+#[async_trait]
+impl CommandImpl for PageMetadata {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        page_metadata(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
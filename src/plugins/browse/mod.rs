@@ -1,25 +1,322 @@
-use std::{error::Error, fmt::Display, collections::HashMap, fs};
+use std::{error::Error, fmt::Display, collections::HashMap, fs, sync::Arc, time::{Duration, Instant}};
 use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures::{stream, StreamExt};
 use colored::Colorize;
-use reqwest::{Client, header::{USER_AGENT, HeaderMap}};
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::{Client, Url, Method, Response, header::{USER_AGENT, CONTENT_TYPE, HeaderMap}, redirect::Policy, Proxy};
+use regex::Regex;
 use textwrap::wrap;
+use tokio::time::sleep;
 
 mod extract;
+mod feed;
+mod cookies;
+mod table;
+mod stats;
+mod condense;
+mod paginate;
+mod injection;
+mod meta;
 
 pub use extract::*;
+pub use feed::*;
+pub use cookies::*;
+pub use table::*;
+pub use stats::*;
+pub use condense::*;
+pub use paginate::*;
+pub use injection::*;
+pub use meta::*;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 
-use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, apply_chunks, PluginData, PluginDataNoInvoke, PluginCycle, invoke, ScriptValue, CommandArgument, Message};
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, apply_chunks, PluginData, PluginDataNoInvoke, PluginCycle, invoke, ScriptValue, CommandArgument, Message, CommandNoArgError, CommandArgs, Citation, attach_citations, report_progress, check_config, CannotConvertError};
 
 pub struct BrowseData {
-    pub client: Client
+    pub client: Client,
+    pub max_links: usize,
+    pub crawl_delay: Duration,
+    pub last_fetches: HashMap<String, Instant>,
+    pub max_body_size: usize,
+    pub markdown_length: usize,
+    pub max_feed_items: usize,
+    pub cookies: Option<Arc<ClearableJar>>,
+    pub summarize_over: Option<usize>,
+    pub max_pages: usize,
+    pub injection_filter: InjectionFilter,
+    pub injection_patterns: Vec<Regex>
+}
+
+impl BrowseData {
+    /// Claims the next fetch for the URL's host, and returns how long to wait so the crawl delay is respected.
+    pub fn schedule_fetch(&mut self, url: &str) -> Duration {
+        let host = match Url::parse(url) {
+            Ok(url) => url.host_str().map(|el| el.to_string()),
+            Err(_) => None
+        };
+
+        let now = Instant::now();
+        match host {
+            Some(host) => {
+                let start = match self.last_fetches.get(&host) {
+                    Some(last_fetch) => (*last_fetch + self.crawl_delay).max(now),
+                    None => now
+                };
+                self.last_fetches.insert(host, start);
+                start - now
+            }
+            None => Duration::ZERO
+        }
+    }
+
+    /// Waits until the crawl delay has passed since the last request to the same host.
+    pub async fn wait_for_host(&mut self, url: &str) {
+        let delay = self.schedule_fetch(url);
+        sleep(delay).await;
+    }
+
+    pub async fn fetch(&self, request: BrowseRequest) -> Result<String, Box<dyn Error>> {
+        let response = self.send(request).await?;
+        self.read_body(response).await
+    }
+
+    pub async fn fetch_bytes(&self, request: BrowseRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let response = self.send(request).await?;
+        self.read_bytes(response).await
+    }
+
+    pub async fn send(&self, request: BrowseRequest) -> Result<Response, Box<dyn Error>> {
+        let BrowseRequest { url, params, basic_auth } = request;
+
+        let mut request = self.client.get(url).query(&params);
+        if let Some(BasicAuth { username, password }) = basic_auth {
+            request = request.basic_auth(username, password);
+        }
+        Ok(request.send().await.map_err(describe_request_error)?)
+    }
+
+    /// Reads a response's body as text, in the charset its Content-Type gives (UTF-8 otherwise).
+    /// Bodies over the max body size are cut off, with a note saying so.
+    pub async fn read_body(&self, response: Response) -> Result<String, Box<dyn Error>> {
+        let encoding = response_encoding(&response);
+        let (bytes, truncated) = self.read_limited(response).await?;
+
+        let mut text = encoding.decode(&bytes).0.to_string();
+        if truncated {
+            text.push_str(&format!("\n\n[truncated: the page is longer than {} bytes]", self.max_body_size));
+        }
+        Ok(text)
+    }
+
+    /// Reads a response's body. A cut off file would be corrupt, so bodies over the max body size are an error.
+    pub async fn read_bytes(&self, response: Response) -> Result<Vec<u8>, Box<dyn Error>> {
+        let url = response.url().to_string();
+        match self.read_limited(response).await? {
+            (_, true) => Err(Box::new(BodyTooLargeError(url, self.max_body_size))),
+            (bytes, false) => Ok(bytes)
+        }
+    }
+
+    /// Reads up to the max body size, and whether there was more.
+    async fn read_limited(&self, mut response: Response) -> Result<(Vec<u8>, bool), Box<dyn Error>> {
+        let mut bytes: Vec<u8> = vec![];
+        while let Some(chunk) = response.chunk().await? {
+            let remaining = self.max_body_size - bytes.len();
+            if chunk.len() > remaining {
+                bytes.extend_from_slice(&chunk[..remaining]);
+                return Ok((bytes, true));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok((bytes, false))
+    }
+}
+
+fn response_encoding(response: &Response) -> &'static Encoding {
+    response.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|el| el.to_str().ok())
+        .and_then(|el| el.split(';').find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            (key.trim().eq_ignore_ascii_case("charset")).then(|| value.trim().trim_matches('"').to_string())
+        }))
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(UTF_8)
+}
+
+#[derive(Debug, Clone)]
+pub struct BodyTooLargeError(pub String, pub usize);
+
+impl Display for BodyTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the response from '{}' is larger than the max body size of {} bytes.", self.0, self.1)
+    }
+}
+
+impl Error for BodyTooLargeError {}
+
+#[derive(Serialize, Deserialize)]
+pub struct BrowseConfig {
+    #[serde(rename = "max links", default = "default_max_links")] pub max_links: usize,
+    #[serde(rename = "crawl delay", default = "default_crawl_delay")] pub crawl_delay: u64,
+    #[serde(rename = "user agent", default = "default_user_agent")] pub user_agent: String,
+    #[serde(rename = "timeout", default = "default_timeout")] pub timeout: u64,
+    #[serde(rename = "max redirects", default = "default_max_redirects")] pub max_redirects: usize,
+    #[serde(rename = "max body size", default = "default_max_body_size")] pub max_body_size: usize,
+    #[serde(rename = "max idle connections per host", default = "default_max_idle_connections")] pub max_idle_connections: usize,
+    #[serde(rename = "markdown length", default = "default_markdown_length")] pub markdown_length: usize,
+    #[serde(rename = "max feed items", default = "default_max_feed_items")] pub max_feed_items: usize,
+    #[serde(rename = "redirect hosts", default)] pub redirect_hosts: RedirectHosts,
+    #[serde(rename = "allowed hosts", default)] pub allowed_hosts: Vec<String>,
+    #[serde(default)] pub proxy: Option<ProxyConfig>,
+    #[serde(default)] pub cookies: bool,
+    /// Pages with more text than this are summarized by ChatGPT, rather than cut off.
+    #[serde(rename = "summarize over", default)] pub summarize_over: Option<usize>,
+    #[serde(rename = "max pages", default = "default_max_pages")] pub max_pages: usize,
+    #[serde(rename = "injection filter", default)] pub injection_filter: InjectionFilter,
+    /// Regexes for injection attempts, on top of the built-in ones.
+    #[serde(rename = "injection patterns", default)] pub injection_patterns: Vec<String>
+}
+
+fn default_max_links() -> usize {
+    50
+}
+
+fn default_crawl_delay() -> u64 {
+    250
+}
+
+fn default_user_agent() -> String {
+    "SmartGPT v0.0.1".to_string()
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_max_redirects() -> usize {
+    10
+}
+
+fn default_max_body_size() -> usize {
+    5_000_000
+}
+
+fn default_max_idle_connections() -> usize {
+    8
+}
+
+fn default_markdown_length() -> usize {
+    12000
+}
+
+fn default_max_feed_items() -> usize {
+    20
+}
+
+fn default_max_pages() -> usize {
+    5
+}
+
+/// Which hosts a redirect may lead to, on top of the `max redirects` limit.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum RedirectHosts {
+    #[default]
+    #[serde(rename = "any")] Any,
+    #[serde(rename = "same host")] SameHost,
+    #[serde(rename = "allowed hosts")] AllowedHosts
+}
+
+#[derive(Debug, Clone)]
+pub struct RedirectLeftHostsError(pub String, pub String);
+
+impl Display for RedirectLeftHostsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the page at '{}' redirected to '{}', which is not an allowed host for redirects.", self.0, self.1)
+    }
+}
+
+impl Error for RedirectLeftHostsError {}
+
+fn is_allowed_host(host: &str, allowed_hosts: &[String]) -> bool {
+    allowed_hosts.iter().any(|allowed| host == allowed || host.ends_with(&format!(".{allowed}")))
+}
+
+fn create_redirect_policy(max_redirects: usize, redirect_hosts: RedirectHosts, allowed_hosts: Vec<String>) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            return attempt.error("too many redirects");
+        }
+
+        let original = attempt.previous().first().and_then(|el| el.host_str()).unwrap_or("").to_string();
+        let host = attempt.url().host_str().unwrap_or("").to_string();
+        let allowed = match redirect_hosts {
+            RedirectHosts::Any => true,
+            RedirectHosts::SameHost => host == original,
+            RedirectHosts::AllowedHosts => host == original || is_allowed_host(&host, &allowed_hosts)
+        };
+
+        if allowed {
+            attempt.follow()
+        } else {
+            let from = attempt.previous().last().map(|el| el.to_string()).unwrap_or_default();
+            let to = attempt.url().to_string();
+            attempt.error(RedirectLeftHostsError(from, to))
+        }
+    })
+}
+
+/// Surfaces a blocked redirect as its own error, instead of reqwest's generic redirect error.
+fn describe_request_error(err: reqwest::Error) -> Box<dyn Error> {
+    let blocked = err.source()
+        .and_then(|el| el.downcast_ref::<RedirectLeftHostsError>())
+        .cloned();
+    match blocked {
+        Some(blocked) => Box::new(blocked),
+        None => Box::new(err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: Option<String>
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct BrowseRequest {
     pub url: String,
-    pub params: Vec<(String, String)>
+    pub params: Vec<(String, String)>,
+    #[serde(default)] pub basic_auth: Option<BasicAuth>
+}
+
+/// A body that isn't text, like an image, encoded as base64.
+#[derive(Serialize, Deserialize)]
+pub struct BinaryBody {
+    pub content_type: Option<String>,
+    pub data: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BrowseManyRequest {
+    pub requests: Vec<BrowseRequest>,
+    pub concurrency: usize
+}
+
+/// A request with any method, for endpoints that `BrowseRequest`'s GETs can't reach.
+#[derive(Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub body: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>
 }
 
 #[async_trait]
@@ -27,12 +324,98 @@ impl PluginData for BrowseData {
     async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
         match name {
             "browse" => {
-                let BrowseRequest { url, params } = serde_json::from_value(value)?;
-                let res_result = self.client.get(url).query(&params).send().await?;
-                let text = res_result.text().await?;
+                let request: BrowseRequest = serde_json::from_value(value)?;
+                self.wait_for_host(&request.url).await;
+
+                let text = self.fetch(request).await?;
                 
                 Ok(text.into())
             }
+            "clear cache" => {
+                if let Some(cookies) = &self.cookies {
+                    cookies.clear();
+                }
+                Ok(true.into())
+            }
+            "browse binary" => {
+                let request: BrowseRequest = serde_json::from_value(value)?;
+                self.wait_for_host(&request.url).await;
+
+                let response = self.send(request).await?;
+                let content_type = response.headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|el| el.to_str().ok())
+                    .map(|el| el.split(';').next().unwrap_or(el).trim().to_string());
+                let bytes = self.read_bytes(response).await?;
+
+                Ok(serde_json::to_value(BinaryBody {
+                    content_type,
+                    data: STANDARD.encode(bytes)
+                })?)
+            }
+            "browse many" => {
+                let BrowseManyRequest { requests, concurrency } = serde_json::from_value(value)?;
+
+                // Every fetch is scheduled up front, so same-host requests still wait out the crawl delay.
+                let delays = requests.iter()
+                    .map(|el| self.schedule_fetch(&el.url))
+                    .collect::<Vec<_>>();
+
+                let data = &*self;
+                let results: Vec<Result<String, String>> = stream::iter(requests.into_iter().zip(delays))
+                    .map(|(request, delay)| async move {
+                        sleep(delay).await;
+                        data.fetch(request).await.map_err(|err| err.to_string())
+                    })
+                    .buffered(concurrency.max(1))
+                    .collect()
+                    .await;
+
+                Ok(serde_json::to_value(results)?)
+            }
+            "send" => {
+                let HttpRequest { method, url, body } = serde_json::from_value(value)?;
+                self.wait_for_host(&url).await;
+
+                let mut request = self.client.request(Method::from_bytes(method.to_uppercase().as_bytes())?, url);
+                if let Some(body) = body {
+                    request = request.body(body);
+                }
+                let response = request.send().await.map_err(describe_request_error)?;
+                let text = self.read_body(response).await?;
+
+                Ok(text.into())
+            }
+            "filter injection" => {
+                let text: String = serde_json::from_value(value)?;
+                Ok(apply_injection_filter(&text, self.injection_filter, &self.injection_patterns).into())
+            }
+            "filter injections" => {
+                let texts: Vec<String> = serde_json::from_value(value)?;
+                let texts = texts.iter()
+                    .map(|el| apply_injection_filter(el, self.injection_filter, &self.injection_patterns))
+                    .collect::<Vec<_>>();
+                Ok(serde_json::to_value(texts)?)
+            }
+            "find injections" => {
+                let text: String = serde_json::from_value(value)?;
+                Ok(serde_json::to_value(find_injections(&text, &self.injection_patterns))?)
+            }
+            "get max links" => {
+                Ok(self.max_links.into())
+            }
+            "get summarize over" => {
+                Ok(self.summarize_over.into())
+            }
+            "get max pages" => {
+                Ok(self.max_pages.into())
+            }
+            "get markdown length" => {
+                Ok(self.markdown_length.into())
+            }
+            "get max feed items" => {
+                Ok(self.max_feed_items.into())
+            }
             _ => {
                 Err(Box::new(PluginDataNoInvoke("Browse".to_string(), name.to_string())))
             }
@@ -80,19 +463,30 @@ fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
 }
 
 pub async fn browse_url(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
-    let browse_info = ctx.plugin_data.get_data("Browse")?;
-
     let params: [(&str, &str); 0] = [];
     let url: String = args.get(0).ok_or(BrowseNoArgError)?.clone().try_into()?;   
 
+    report_progress(ctx, "browse_url", "Fetching", None);
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
     let body = invoke::<String>(browse_info, "browse", BrowseRequest {
         url: url.to_string(),
         params: params.iter()
             .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>(),
+        basic_auth: None
     }).await?;
 
-    let content = extract_text_from_html(&body);
+    let title = extract_title_from_html(&body).unwrap_or(url.clone());
+    let content = filter_injection(ctx, extract_text_from_html(&body)).await?;
+
+    // With 'summarize over' set, short pages come back as they are, and long ones are condensed.
+    let summarize_over = invoke::<Option<usize>>(ctx.plugin_data.get_data("Browse")?, "get summarize over", true).await?;
+    if summarize_over.is_some() {
+        let content = condense_long_text(ctx, "browse_url", &content).await?.unwrap_or(content);
+        return Ok(attach_citations(ctx, content.into(), vec![
+            Citation::new(&title, &url)
+        ])?);
+    }
 
     let mut summarized_content = String::new();
     let chunks = chunk_text(&content, 11000);
@@ -104,7 +498,7 @@ pub async fn browse_url(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Res
     }.to_string();
 
     for (ind, chunk) in chunks.iter().enumerate() {
-        println!("{} {} / {}", "Summarizing Chunk".green(), ind + 1, chunks.len());
+        report_progress(ctx, "browse_url", "Summarizing Chunk", Some((ind + 1, chunk_count)));
 
         ctx.agents.fast.llm.message_history.clear();
 
@@ -121,7 +515,88 @@ pub async fn browse_url(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Res
         summarized_content.push_str(&response);
     }
 
-    Ok(ScriptValue::String(summarized_content))
+    report_progress(ctx, "browse_url", "Merging Summaries", None);
+    Ok(attach_citations(ctx, ScriptValue::String(summarized_content), vec![
+        Citation::new(&title, &url)
+    ])?)
+}
+
+pub async fn browse_links(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+
+    let url: String = args.get(0).ok_or(CommandNoArgError("browse_links", "url"))?.clone().try_into()?;
+    let same_host: bool = match args.get(1) {
+        Some(same_host) => same_host.clone().try_into()?,
+        None => false
+    };
+    let base = Url::parse(&url)?;
+
+    let max_links = invoke::<usize>(browse_info, "get max links", true).await?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.to_string(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let links = extract_links_from_html(&body, &base, same_host)
+        .into_iter()
+        .take(max_links)
+        .collect::<Vec<_>>();
+    let links = filter_injections(ctx, links).await?;
+
+    Ok(ScriptValue::List(
+        links.into_iter()
+            .map(|el| el.into())
+            .collect()
+    ))
+}
+
+pub async fn browse_select(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let url: String = args.get(0).ok_or(CommandNoArgError("browse_select", "url"))?.clone().try_into()?;
+    let selectors: HashMap<String, ScriptValue> = args.get(1).ok_or(CommandNoArgError("browse_select", "selectors"))?.clone().try_into()?;
+    let selectors = selectors.into_iter()
+        .map(|(field, selector)| Ok((field, selector.try_into()?)))
+        .collect::<Result<HashMap<String, String>, CannotConvertError>>()?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.to_string(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let fields = extract_selectors_from_html(&body, &selectors)?;
+
+    Ok(ScriptValue::Dict(
+        fields.into_iter()
+            .map(|(field, text)| (field, text.map(|el| el.into()).unwrap_or(ScriptValue::None)))
+            .collect()
+    ))
+}
+
+pub async fn browse_markdown(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("browse_markdown", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let markdown_length = invoke::<usize>(browse_info, "get markdown length", true).await?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let title = extract_title_from_html(&body).unwrap_or(url.clone());
+    // Filtered before condensing, so that the condensing model never reads the injected text.
+    let full_markdown = filter_injection(ctx, extract_markdown_from_html(&body, usize::MAX)).await?;
+    let markdown = match condense_long_text(ctx, "browse_markdown", &full_markdown).await? {
+        Some(summary) => summary,
+        None => filter_injection(ctx, extract_markdown_from_html(&body, markdown_length)).await?
+    };
+
+    Ok(attach_citations(ctx, markdown.into(), vec![
+        Citation::new(&title, &url)
+    ])?)
 }
 
 pub struct BrowseURL;
@@ -137,6 +612,45 @@ impl CommandImpl for BrowseURL {
     }
 }
 
+pub struct BrowseLinks;
+
+#[async_trait]
+impl CommandImpl for BrowseLinks {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_links(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct BrowseSelect;
+
+#[async_trait]
+impl CommandImpl for BrowseSelect {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_select(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct BrowseMarkdown;
+
+#[async_trait]
+impl CommandImpl for BrowseMarkdown {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_markdown(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
 pub struct BrowseCycle;
 
 #[async_trait]
@@ -145,17 +659,62 @@ impl PluginCycle for BrowseCycle {
         Ok(None)
     }
 
-    fn create_data(&self, _: Value) -> Option<Box<dyn PluginData>> {
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let config: BrowseConfig = serde_json::from_value(value)?;
+
         let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, "SmartGPT v0.0.1".parse().unwrap());
+        headers.insert(USER_AGENT, config.user_agent.parse()?);
     
-        let client = reqwest::Client::builder()
+        // This one client is shared by every request, so that connections are pooled and reused.
+        let mut client = reqwest::Client::builder()
             .default_headers(headers)
-            .build().unwrap();
+            .timeout(Duration::from_secs(config.timeout))
+            .redirect(create_redirect_policy(config.max_redirects, config.redirect_hosts, config.allowed_hosts.clone()))
+            .pool_max_idle_per_host(config.max_idle_connections);
+
+        if let Some(ProxyConfig { url, username, password }) = config.proxy {
+            let mut proxy = Proxy::all(url)?;
+            if let Some(username) = username {
+                proxy = proxy.basic_auth(&username, password.as_deref().unwrap_or(""));
+            }
+            client = client.proxy(proxy);
+        }
 
-        Some(Box::new(BrowseData {
-            client
-        }))
+        // Cookies set by one fetch are sent on later fetches to the same host, for pages behind a session.
+        let cookies = match config.cookies {
+            true => {
+                let jar = Arc::new(ClearableJar::default());
+                client = client.cookie_provider(jar.clone());
+                Some(jar)
+            }
+            false => None
+        };
+
+        let client = client.build()?;
+
+        Ok(Some(Box::new(BrowseData {
+            client,
+            max_links: config.max_links,
+            crawl_delay: Duration::from_millis(config.crawl_delay),
+            last_fetches: HashMap::new(),
+            max_body_size: config.max_body_size,
+            markdown_length: config.markdown_length,
+            max_feed_items: config.max_feed_items,
+            cookies,
+            summarize_over: config.summarize_over,
+            max_pages: config.max_pages,
+            injection_filter: config.injection_filter,
+            injection_patterns: compile_injection_patterns(&config.injection_patterns)?
+        })))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<BrowseConfig>(value.clone())?;
+
+        let config: BrowseConfig = serde_json::from_value(value)?;
+        compile_injection_patterns(&config.injection_patterns)?;
+
+        Ok(())
     }
 }
 
@@ -163,6 +722,7 @@ pub fn create_browse() -> Plugin {
     Plugin {
         name: "Browse".to_string(),
         dependencies: vec![],
+        default_timeout: Some(Duration::from_secs(120)),
         cycle: Box::new(BrowseCycle),
         commands: vec![
             Command {
@@ -171,8 +731,104 @@ pub fn create_browse() -> Plugin {
                 args: vec![
                     CommandArgument::new("url", "The URL to browse.", "String")
                 ],
-                return_type: "String".to_string(),
+                return_type: "{ content: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
                 run: Box::new(BrowseURL)
+            },
+            Command {
+                name: "browse_links".to_string(),
+                purpose: "Get the deduplicated list of absolute links on a page.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL to get the links of.", "String"),
+                    CommandArgument::optional("same_host", "Optional. Whether to only include links to the same host.", "bool")
+                ],
+                return_type: "String[]".to_string(),
+                run: Box::new(BrowseLinks)
+            },
+            Command {
+                name: "browse_select".to_string(),
+                purpose: "Extract specific fields from a page using CSS selectors.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL to extract from.", "String"),
+                    CommandArgument::new("selectors", "A map of field names to CSS selectors.", "{ [field: String]: String }")
+                ],
+                return_type: "{ [field: String]: String | None }".to_string(),
+                run: Box::new(BrowseSelect)
+            },
+            Command {
+                name: "browse_markdown".to_string(),
+                purpose: "Browse the main content of a page as Markdown, keeping headings, lists, tables, and code blocks.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL to browse.", "String")
+                ],
+                return_type: "{ content: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(BrowseMarkdown)
+            },
+            Command {
+                name: "browse_feed".to_string(),
+                purpose: "Get the latest items of an RSS or Atom feed.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the feed.", "String")
+                ],
+                return_type: "{ title: String | None, link: String | None, published: String | None, summary: String | None }[]".to_string(),
+                run: Box::new(BrowseFeed)
+            },
+            Command {
+                name: "browse_table".to_string(),
+                purpose: "Extract a table from a page as rows keyed by column header.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the page.", "String"),
+                    CommandArgument::optional("index", "Optional. Which table to take, counting from 0. Defaults to the first.", "Int"),
+                    CommandArgument::optional("selector", "Optional. A CSS selector for the table, instead of an index.", "String"),
+                    CommandArgument::optional("csv", "Optional. Whether to also return the table as CSV.", "bool")
+                ],
+                return_type: "{ headers: String[], rows: { [header: String]: String }[], csv?: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(BrowseTable)
+            },
+            Command {
+                name: "browse_pages".to_string(),
+                purpose: "Read a paginated listing, following its \"next\" links and joining the pages' content.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the first page.", "String"),
+                    CommandArgument::optional("max_pages", "Optional. The most pages to read.", "Int"),
+                    CommandArgument::optional("next_selector", "Optional. A CSS selector for the next link. Defaults to rel=\"next\" links or links reading \"next\".", "String"),
+                    CommandArgument::optional("content_selector", "Optional. A CSS selector for the content to take from each page. Defaults to the page's text.", "String")
+                ],
+                return_type: "{ content: String, pages: String[], stopped: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(BrowsePages)
+            },
+            Command {
+                name: "page_stats".to_string(),
+                purpose: "Get a page's word count, reading time, and language, to decide whether it's worth reading.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the page.", "String")
+                ],
+                return_type: "{ title: String, word_count: Int, reading_minutes: Float, language: String | None }".to_string(),
+                run: Box::new(PageStats)
+            },
+            Command {
+                name: "page_metadata".to_string(),
+                purpose: "Get a page's OpenGraph metadata, like its title, description, and main image, to judge a source quickly.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the page.", "String")
+                ],
+                return_type: "{ url: String, title: String | None, description: String | None, image: String | None, site_name: String | None, published_time: String | None }".to_string(),
+                run: Box::new(PageMetadata)
+            },
+            Command {
+                name: "clear_browse_cache".to_string(),
+                purpose: "Forget the cookies that pages have set, to start browsing without a session.".to_string(),
+                args: vec![],
+                return_type: "String".to_string(),
+                run: Box::new(ClearBrowseCache)
+            },
+            Command {
+                name: "detect_injection".to_string(),
+                purpose: "Check text for prompt-injection attempts, like \"ignore previous instructions\".".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to check.", "String")
+                ],
+                return_type: "{ flagged: bool, matches: String[] }".to_string(),
+                run: Box::new(DetectInjection)
             }
         ]
     }
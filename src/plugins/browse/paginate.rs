@@ -0,0 +1,150 @@
+use std::{error::Error, collections::{HashMap, HashSet}};
+
+use async_trait::async_trait;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, invoke, attach_citations, report_progress};
+
+use super::{BrowseRequest, InvalidSelectorError, extract_text_from_html, extract_title_from_html, filter_injection};
+
+/// Link texts that mean "the next page", for pages that don't mark their next link with `rel="next"`.
+const NEXT_LINK_TEXTS: [&str; 7] = [ "next", "next page", "next »", "next ›", "»", "›", "older posts" ];
+
+/// Finds the link to the next page: the first match of `selector` if one is given, or else a `rel="next"`
+/// link, or else a link whose text reads like "next".
+pub fn find_next_link(html: &str, base: &Url, selector: Option<&str>) -> Result<Option<Url>, InvalidSelectorError> {
+    let document = Html::parse_document(html);
+
+    let href = match selector {
+        Some(selector) => {
+            let parsed = Selector::parse(selector)
+                .map_err(|_| InvalidSelectorError("next".to_string(), selector.to_string()))?;
+            document.select(&parsed)
+                .find_map(|el| el.value().attr("href"))
+        }
+        None => {
+            let rel_next = Selector::parse("a[rel~=next], link[rel~=next]").unwrap();
+            let anchors = Selector::parse("a[href]").unwrap();
+
+            document.select(&rel_next)
+                .find_map(|el| el.value().attr("href"))
+                .or_else(|| document.select(&anchors)
+                    .find(|el| {
+                        let text = el.text().collect::<String>().trim().to_lowercase();
+                        NEXT_LINK_TEXTS.contains(&text.as_str())
+                    })
+                    .and_then(|el| el.value().attr("href"))
+                )
+        }
+    };
+
+    Ok(href.and_then(|el| base.join(el.trim()).ok()))
+}
+
+/// The text of every element matching `selector`, one per paragraph.
+fn extract_selected_text(html: &str, selector: &str) -> Result<String, InvalidSelectorError> {
+    let document = Html::parse_document(html);
+    let parsed = Selector::parse(selector)
+        .map_err(|_| InvalidSelectorError("content".to_string(), selector.to_string()))?;
+
+    Ok(
+        document.select(&parsed)
+            .map(|el| el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|el| !el.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    )
+}
+
+pub async fn browse_pages(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("browse_pages", &[ "url", "max_pages", "next_selector", "content_selector" ], &args);
+    let url = args.require_string("url")?;
+    let next_selector = args.optional_string("next_selector")?;
+    let content_selector = args.optional_string("content_selector")?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let limit = invoke::<usize>(browse_info, "get max pages", true).await?;
+    let max_pages = args.optional_int("max_pages")?
+        .map(|el| (el.max(1) as usize).min(limit))
+        .unwrap_or(limit);
+
+    let mut pages: Vec<String> = vec![];
+    let mut contents: Vec<String> = vec![];
+    let mut citations: Vec<Citation> = vec![];
+    let mut visited: HashSet<String> = HashSet::new();
+    let mut next = Some(Url::parse(&url)?);
+    let mut stopped = "reached the page limit".to_string();
+
+    while let Some(page) = next.take() {
+        if pages.len() >= max_pages {
+            break;
+        }
+        if !visited.insert(page.to_string()) {
+            stopped = "the next link led back to a page already read".to_string();
+            break;
+        }
+
+        report_progress(ctx, "browse_pages", "Fetching", Some((pages.len() + 1, max_pages)));
+        let browse_info = ctx.plugin_data.get_data("Browse")?;
+        let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+            url: page.to_string(),
+            params: vec![],
+            basic_auth: None
+        }).await;
+
+        // The first page failing is an error, but a later one just ends the listing with what was gathered.
+        let body = match body {
+            Ok(body) => body,
+            Err(err) if pages.len() > 0 => {
+                stopped = format!("could not fetch '{page}': {err}");
+                break;
+            }
+            Err(err) => return Err(err)
+        };
+
+        let content = match &content_selector {
+            Some(selector) => extract_selected_text(&body, selector)?,
+            None => extract_text_from_html(&body)
+        };
+        let content = filter_injection(ctx, content).await?;
+        let title = extract_title_from_html(&body).unwrap_or(page.to_string());
+
+        contents.push(content);
+        citations.push(Citation::new(&title, page.as_str()));
+        pages.push(page.to_string());
+
+        next = find_next_link(&body, &page, next_selector.as_deref())?;
+        if next.is_none() {
+            stopped = "no next link was found".to_string();
+        }
+    }
+
+    let content = pages.iter()
+        .zip(&contents)
+        .enumerate()
+        .map(|(ind, (page, content))| format!("Page {} ({page}):\n{content}", ind + 1))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let out = HashMap::from_iter([
+        ("content".to_string(), content.into()),
+        ("pages".to_string(), ScriptValue::List(pages.into_iter().map(|el| el.into()).collect())),
+        ("stopped".to_string(), stopped.into())
+    ]);
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(out), citations)?)
+}
+
+pub struct BrowsePages;
+
+#[async_trait]
+impl CommandImpl for BrowsePages {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_pages(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
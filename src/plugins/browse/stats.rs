@@ -0,0 +1,56 @@
+use std::{error::Error, collections::HashMap};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke, detect_text_language};
+
+use super::{BrowseRequest, extract_paragraphs_from_html, extract_title_from_html};
+
+/// An average adult's silent reading speed.
+const WORDS_PER_MINUTE: f64 = 238.;
+
+pub async fn page_stats(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("page_stats", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    // Everything is worked out locally; none of the page is sent to a model.
+    let text = extract_paragraphs_from_html(&body).join("\n");
+    let word_count = text.split_whitespace().count();
+    let reading_minutes = (word_count as f64 / WORDS_PER_MINUTE * 10.).round() / 10.;
+
+    let language = match detect_text_language(&text) {
+        Some((lang, _)) => lang.code().to_string().into(),
+        None => ScriptValue::None
+    };
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("title".to_string(), extract_title_from_html(&body).unwrap_or(url).into()),
+        ("word_count".to_string(), ScriptValue::Int(word_count as i64)),
+        ("reading_minutes".to_string(), ScriptValue::Float(reading_minutes)),
+        ("language".to_string(), language)
+    ])))
+}
+
+pub struct PageStats;
+
+#[async_trait]
+impl CommandImpl for PageStats {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        page_stats(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,203 @@
+use std::{error::Error, collections::HashMap, fmt::Display};
+
+use async_trait::async_trait;
+use scraper::{Html, Selector, ElementRef};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, invoke, attach_citations};
+
+use super::{BrowseRequest, InvalidSelectorError, extract_title_from_html, filter_injections};
+
+#[derive(Debug, Clone)]
+pub struct NoTableError(pub String, pub String);
+
+impl Display for NoTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the page at '{}' has no table {}.", self.0, self.1)
+    }
+}
+
+impl Error for NoTableError {}
+
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>
+}
+
+fn cell_text(cell: &ElementRef) -> String {
+    cell.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether the element's closest enclosing table is `table`, so rows of nested tables aren't mixed in.
+fn belongs_to(element: &ElementRef, table: &ElementRef) -> bool {
+    element.ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|el| el.value().name() == "table")
+        .map(|el| el.id() == table.id())
+        .unwrap_or(false)
+}
+
+fn parse_table(table: ElementRef) -> Table {
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("th, td").unwrap();
+
+    let mut header_row: Option<Vec<String>> = None;
+    let mut rows: Vec<Vec<String>> = vec![];
+    for row in table.select(&row_selector).filter(|el| belongs_to(el, &table)) {
+        let cells = row.select(&cell_selector)
+            .filter(|el| belongs_to(el, &table))
+            .collect::<Vec<_>>();
+        if cells.len() == 0 {
+            continue;
+        }
+
+        // The first row is the header if it's in a <thead>, or made only of <th> cells.
+        let in_head = row.ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|el| el.value().name() == "thead");
+        let all_th = cells.iter().all(|el| el.value().name() == "th");
+        let texts = cells.iter().map(cell_text).collect::<Vec<_>>();
+
+        if header_row.is_none() && rows.len() == 0 && (in_head || all_th) {
+            header_row = Some(texts);
+        } else {
+            rows.push(texts);
+        }
+    }
+
+    // Ragged rows are padded to the widest row, and columns without a header get a numbered one.
+    let width = rows.iter()
+        .map(|el| el.len())
+        .chain(header_row.iter().map(|el| el.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut headers = header_row.unwrap_or_default();
+    for ind in 0..width {
+        match headers.get_mut(ind) {
+            Some(header) if !header.is_empty() => {}
+            Some(header) => *header = format!("column_{}", ind + 1),
+            None => headers.push(format!("column_{}", ind + 1))
+        }
+    }
+
+    // Repeated headers would overwrite each other in the row dicts, so later ones get a suffix.
+    for ind in 1..headers.len() {
+        let repeats = headers[..ind].iter().filter(|el| **el == headers[ind]).count();
+        if repeats > 0 {
+            headers[ind] = format!("{}_{}", headers[ind], repeats + 1);
+        }
+    }
+
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+
+    Table { headers, rows }
+}
+
+/// Finds the table matching `selector`, or else the `index`th table on the page.
+pub fn extract_table_from_html(html: &str, index: usize, selector: Option<&str>) -> Result<Option<Table>, InvalidSelectorError> {
+    let document = Html::parse_document(html);
+
+    let parsed = match selector {
+        Some(selector) => Selector::parse(selector)
+            .map_err(|_| InvalidSelectorError("table".to_string(), selector.to_string()))?,
+        None => Selector::parse("table").unwrap()
+    };
+
+    let table = document.select(&parsed)
+        .filter(|el| el.value().name() == "table")
+        .nth(index);
+
+    Ok(table.map(parse_table))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl Table {
+    pub fn to_csv(&self) -> String {
+        std::iter::once(&self.headers)
+            .chain(&self.rows)
+            .map(|row| row.iter().map(|el| csv_field(el)).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub async fn browse_table(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("browse_table", &[ "url", "index", "selector", "csv" ], &args);
+    let url = args.require_string("url")?;
+    let index = args.optional_int("index")?.unwrap_or(0).max(0) as usize;
+    let selector = args.optional_string("selector")?;
+    let csv = args.optional_bool("csv")?.unwrap_or(false);
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let mut table = extract_table_from_html(&body, index, selector.as_deref())?
+        .ok_or_else(|| NoTableError(url.clone(), match &selector {
+            Some(selector) => format!("matching '{selector}'"),
+            None => format!("at index {index}")
+        }))?;
+
+    // Every header and cell goes through the injection filter, then gets put back in place.
+    let width = table.headers.len();
+    let cells = std::iter::once(&table.headers)
+        .chain(&table.rows)
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut cells = filter_injections(ctx, cells).await?.into_iter();
+    table.headers = cells.by_ref().take(width).collect();
+    for row in table.rows.iter_mut() {
+        let len = row.len();
+        *row = cells.by_ref().take(len).collect();
+    }
+
+    let rows = table.rows.iter()
+        .map(|row| ScriptValue::Dict(
+            table.headers.iter()
+                .zip(row)
+                .map(|(header, cell)| (header.clone(), cell.clone().into()))
+                .collect::<HashMap<_, _>>()
+        ))
+        .collect::<Vec<_>>();
+
+    let mut out = HashMap::from_iter([
+        ("headers".to_string(), ScriptValue::List(table.headers.iter().map(|el| el.clone().into()).collect())),
+        ("rows".to_string(), ScriptValue::List(rows))
+    ]);
+    if csv {
+        out.insert("csv".to_string(), table.to_csv().into());
+    }
+
+    let title = extract_title_from_html(&body).unwrap_or(url.clone());
+    Ok(attach_citations(ctx, ScriptValue::Dict(out), vec![ Citation::new(&title, &url) ])?)
+}
+
+pub struct BrowseTable;
+
+#[async_trait]
+impl CommandImpl for BrowseTable {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        browse_table(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue};
+
+use super::{ask_chatgpt_ephemeral, parse_model_json};
+
+const CALC_PROMPT: &str = r#"You turn math problems into arithmetic expressions. Write one expression that computes the answer to the problem the user sends you, and what it evaluates to.
+
+The expression may only use numbers, + - * / ^ %, parentheses, the constants pi and e, and the functions sqrt, abs, exp, ln, floor, ceil, round, sin, cos, tan, min, and max.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "expression": "...",
+    "answer": 0.0
+}"#;
+
+/// How far the model's answer may be from the evaluated one, relative to its size, before it is flagged.
+const TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone)]
+pub struct CalcExpressionError(pub String, pub String);
+
+impl Display for CalcExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChatGPT's expression '{}' could not be evaluated: {}", self.0, self.1)
+    }
+}
+
+impl Error for CalcExpressionError {}
+
+#[derive(Serialize, Deserialize)]
+pub struct Calculation {
+    pub expression: String,
+    #[serde(default)] pub answer: Option<f64>
+}
+
+pub async fn calc(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("calc", &[ "problem" ], &args);
+    let problem = args.require_string("problem")?;
+
+    let response = ask_chatgpt_ephemeral(ctx, CALC_PROMPT, &problem).await?;
+    let calculation: Calculation = parse_model_json(ctx, &response).await?;
+
+    // The model's arithmetic isn't trusted; its expression is evaluated here, and that result is what's returned.
+    let answer = meval::eval_str(&calculation.expression)
+        .map_err(|err| CalcExpressionError(calculation.expression.clone(), err.to_string()))?;
+    if !answer.is_finite() {
+        return Err(Box::new(CalcExpressionError(calculation.expression, "the result is not a finite number".to_string())));
+    }
+
+    let verified = match calculation.answer {
+        Some(model_answer) => (model_answer - answer).abs() <= TOLERANCE * answer.abs().max(1.),
+        None => false
+    };
+
+    let mut out = HashMap::from_iter([
+        ("expression".to_string(), calculation.expression.into()),
+        ("answer".to_string(), ScriptValue::Float(answer)),
+        ("verified".to_string(), verified.into())
+    ]);
+    if !verified {
+        out.insert("model_answer".to_string(), match calculation.answer {
+            Some(model_answer) => ScriptValue::Float(model_answer),
+            None => ScriptValue::None
+        });
+        out.insert("note".to_string(), "ChatGPT's own answer did not match its expression; the answer given is the evaluated expression.".to_string().into());
+    }
+
+    Ok(ScriptValue::Dict(out))
+}
+
+pub struct CalcImpl;
+
+#[async_trait]
+impl CommandImpl for CalcImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        calc(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
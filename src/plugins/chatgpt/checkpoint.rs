@@ -0,0 +1,303 @@
+use std::{error::Error, fmt::Display, fs, path::{Path, PathBuf}, time::{Duration, Instant}, collections::HashMap};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ChatGPTData, ChatGPTMessage};
+
+const CHECKPOINT_PREFIX: &str = "checkpoint-";
+
+#[derive(Debug, Clone)]
+pub struct NoCheckpointDirectoryError;
+
+impl Display for NoCheckpointDirectoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the ChatGPT plugin has no checkpoint 'directory' configured.")
+    }
+}
+
+impl Error for NoCheckpointDirectoryError {}
+
+#[derive(Debug, Clone)]
+pub struct NoCheckpointError(pub String);
+
+impl Display for NoCheckpointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "there is no checkpoint named '{}'. Use list_checkpoints to see them.", self.0)
+    }
+}
+
+impl Error for NoCheckpointError {}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CheckpointConfig {
+    /// Where checkpoints are saved. Nothing is saved without it.
+    #[serde(default)] pub directory: Option<String>,
+    #[serde(rename = "every responses", default)] pub every_responses: Option<usize>,
+    /// Checked whenever ChatGPT responds, so a checkpoint is never saved while nothing is changing.
+    #[serde(rename = "every seconds", default)] pub every_seconds: Option<u64>,
+    #[serde(default = "default_keep_checkpoints")] pub keep: usize,
+    #[serde(rename = "load latest", default)] pub load_latest: bool
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            every_responses: None,
+            every_seconds: None,
+            keep: default_keep_checkpoints(),
+            load_latest: false
+        }
+    }
+}
+
+fn default_keep_checkpoints() -> usize {
+    5
+}
+
+/// A snapshot of ChatGPT's memory, as saved to disk.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    pub created: String,
+    pub messages: Vec<ChatGPTMessage>
+}
+
+pub struct Checkpoints {
+    pub config: CheckpointConfig,
+    pub responses: usize,
+    pub last_saved: Instant
+}
+
+impl Checkpoints {
+    pub fn new(config: CheckpointConfig) -> Self {
+        Self {
+            config,
+            responses: 0,
+            last_saved: Instant::now()
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        let by_responses = self.config.every_responses.map_or(false, |el| self.responses >= el.max(1));
+        let by_time = self.config.every_seconds.map_or(false, |el| self.last_saved.elapsed() >= Duration::from_secs(el));
+        by_responses || by_time
+    }
+}
+
+/// Every checkpoint in the directory, oldest first. Their names sort by when they were saved.
+pub fn list_checkpoint_files(directory: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let directory = Path::new(directory);
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut files = fs::read_dir(directory)?
+        .filter_map(|el| el.ok())
+        .map(|el| el.path())
+        .filter(|el| {
+            let name = el.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            name.starts_with(CHECKPOINT_PREFIX) && name.ends_with(".json")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+}
+
+/// A checkpoint's name is its file name without the extension.
+fn checkpoint_name(path: &Path) -> String {
+    path.file_stem().and_then(|el| el.to_str()).unwrap_or("").to_string()
+}
+
+/// Checkpoints are only found by name among the listed files, so a name can't point outside the directory.
+fn find_checkpoint(directory: &str, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = list_checkpoint_files(directory)?.into_iter()
+        .find(|el| checkpoint_name(el) == name)
+        .ok_or(NoCheckpointError(name.to_string()))?;
+    Ok(path)
+}
+
+/// What a checkpoint holds, without its messages.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CheckpointSummary {
+    pub name: String,
+    pub created: String,
+    pub messages: usize
+}
+
+pub fn read_checkpoint(path: &Path) -> Result<Checkpoint, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// The newest checkpoint that can be read. Corrupt ones are skipped with a warning.
+pub fn load_latest_checkpoint(directory: &str) -> Result<Option<Checkpoint>, Box<dyn Error>> {
+    for path in list_checkpoint_files(directory)?.into_iter().rev() {
+        match read_checkpoint(&path) {
+            Ok(checkpoint) => return Ok(Some(checkpoint)),
+            Err(err) => println!("{}: Skipping the corrupt checkpoint '{}': {}", "Warning".yellow(), path.display(), err)
+        }
+    }
+
+    Ok(None)
+}
+
+impl ChatGPTData {
+    fn checkpoint_directory(&self) -> Result<String, NoCheckpointDirectoryError> {
+        self.checkpoints.config.directory.clone().ok_or(NoCheckpointDirectoryError)
+    }
+
+    /// Every readable checkpoint, oldest first. Corrupt ones are skipped with a warning.
+    pub fn list_checkpoints(&self) -> Result<Vec<CheckpointSummary>, Box<dyn Error>> {
+        let directory = self.checkpoint_directory()?;
+
+        let mut checkpoints = vec![];
+        for path in list_checkpoint_files(&directory)? {
+            match read_checkpoint(&path) {
+                Ok(checkpoint) => checkpoints.push(CheckpointSummary {
+                    name: checkpoint_name(&path),
+                    created: checkpoint.created,
+                    messages: checkpoint.messages.len()
+                }),
+                Err(err) => println!("{}: Skipping the corrupt checkpoint '{}': {}", "Warning".yellow(), path.display(), err)
+            }
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// Replaces memory with a checkpoint's messages, returning how many there are.
+    pub fn load_checkpoint(&mut self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let path = find_checkpoint(&self.checkpoint_directory()?, name)?;
+        let checkpoint = read_checkpoint(&path)?;
+
+        self.memory = checkpoint.messages.into_iter().map(|el| el.into()).collect();
+        Ok(self.memory.len())
+    }
+
+    pub fn delete_checkpoint(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let path = find_checkpoint(&self.checkpoint_directory()?, name)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Saves memory as a new checkpoint, then deletes the oldest ones past the number to keep.
+    pub fn save_checkpoint(&mut self) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        let Some(directory) = self.checkpoints.config.directory.clone() else {
+            return Ok(None);
+        };
+        fs::create_dir_all(&directory)?;
+
+        let now = Utc::now();
+        let checkpoint = Checkpoint {
+            created: now.to_rfc3339(),
+            messages: self.memory.iter().cloned().map(|el| el.into()).collect()
+        };
+
+        // Written under another name first, so that a crash can't leave a half-written checkpoint as the latest.
+        let path = Path::new(&directory).join(format!("{CHECKPOINT_PREFIX}{}.json", now.format("%Y%m%d-%H%M%S%3f")));
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_string(&checkpoint)?)?;
+        fs::rename(&partial, &path)?;
+
+        self.checkpoints.responses = 0;
+        self.checkpoints.last_saved = Instant::now();
+
+        let files = list_checkpoint_files(&directory)?;
+        let excess = files.len().saturating_sub(self.checkpoints.config.keep.max(1));
+        for old in &files[..excess] {
+            fs::remove_file(old)?;
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Called after each response. A failed checkpoint is only a warning, since the response itself succeeded.
+    pub fn checkpoint_if_due(&mut self) {
+        self.checkpoints.responses += 1;
+        if !self.checkpoints.is_due() {
+            return;
+        }
+
+        if let Err(err) = self.save_checkpoint() {
+            println!("{}: Could not save a ChatGPT checkpoint: {}", "Warning".yellow(), err);
+        }
+    }
+}
+
+pub async fn list_checkpoints(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let checkpoints = invoke::<Vec<CheckpointSummary>>(chatgpt_info, "list checkpoints", true).await?;
+
+    let checkpoints = checkpoints.into_iter()
+        .map(|el| ScriptValue::Dict(HashMap::from_iter([
+            ("name".to_string(), el.name.into()),
+            ("created".to_string(), el.created.into()),
+            ("messages".to_string(), ScriptValue::Int(el.messages as i64))
+        ])))
+        .collect::<Vec<_>>();
+    Ok(checkpoints.into())
+}
+
+pub async fn load_checkpoint(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("load_checkpoint", &[ "name" ], &args);
+    let name = args.require_string("name")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let length = invoke::<usize>(chatgpt_info, "load checkpoint", &name).await?;
+
+    Ok(format!("Loaded {length} messages from the checkpoint '{name}'.").into())
+}
+
+pub async fn delete_checkpoint(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("delete_checkpoint", &[ "name" ], &args);
+    let name = args.require_string("name")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    invoke::<bool>(chatgpt_info, "delete checkpoint", &name).await?;
+
+    Ok(format!("Deleted the checkpoint '{name}'.").into())
+}
+
+pub struct ListCheckpointsImpl;
+
+#[async_trait]
+impl CommandImpl for ListCheckpointsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        list_checkpoints(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct LoadCheckpointImpl;
+
+#[async_trait]
+impl CommandImpl for LoadCheckpointImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        load_checkpoint(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct DeleteCheckpointImpl;
+
+#[async_trait]
+impl CommandImpl for DeleteCheckpointImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        delete_checkpoint(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
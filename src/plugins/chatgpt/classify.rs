@@ -0,0 +1,68 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue};
+
+use super::{ask_chatgpt_ephemeral, parse_model_json};
+
+const CLASSIFY_PROMPT: &str = r#"You are a text classifier. You will be given a list of categories and a text. Pick the one category that fits the text best.
+
+If no category fits, use "none" as the category.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "category": "...",
+    "confidence": 0.0 to 1.0,
+    "explanation": "..."
+}"#;
+
+#[derive(Serialize, Deserialize)]
+pub struct Classification {
+    pub category: String,
+    #[serde(default)] pub confidence: f64,
+    #[serde(default)] pub explanation: String
+}
+
+pub async fn classify(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("classify", &[ "text", "categories" ], &args);
+    let text = args.require_string("text")?;
+    let categories: Vec<String> = args.require_as("categories", "a list of strings")?;
+
+    let query = format!(
+        "Categories:\n{}\n\nText:\n{text}",
+        categories.iter()
+            .map(|el| format!("- {el}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let response = ask_chatgpt_ephemeral(ctx, CLASSIFY_PROMPT, &query).await?;
+    let classification: Classification = parse_model_json(ctx, &response).await?;
+
+    // Anything outside of the given categories, including "none", means nothing fit.
+    let category = categories.iter()
+        .find(|el| el.trim().eq_ignore_ascii_case(classification.category.trim()))
+        .map(|el| el.clone().into())
+        .unwrap_or(ScriptValue::None);
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("category".to_string(), category),
+        ("confidence".to_string(), ScriptValue::Float(classification.confidence.clamp(0., 1.))),
+        ("explanation".to_string(), classification.explanation.into())
+    ])))
+}
+
+pub struct ClassifyImpl;
+
+#[async_trait]
+impl CommandImpl for ClassifyImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        classify(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,130 @@
+use std::{error::Error, fs, time::Duration};
+
+use colored::Colorize;
+use tokio::time::sleep;
+
+use async_openai::{Client, error::{OpenAIError, ApiError}};
+use reqwest::{Proxy, Certificate};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::ProxyConfig;
+
+use super::ChatGPTData;
+
+const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+
+/// How many times a request is sent before a rate limit or server error is given up on.
+const MAX_ATTEMPTS: u32 = 6;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Settings for the HTTP client that OpenAI requests are sent through, for networks that need a proxy or their own certificates.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct OpenAIClientConfig {
+    #[serde(rename = "api base", default)] pub api_base: Option<String>,
+    #[serde(default)] pub organization: Option<String>,
+    #[serde(default)] pub proxy: Option<ProxyConfig>,
+    #[serde(default)] pub timeout: Option<u64>,
+    #[serde(rename = "root certificates", default)] pub root_certificates: Vec<String>,
+    #[serde(rename = "accept invalid certificates", default)] pub accept_invalid_certificates: bool
+}
+
+impl OpenAIClientConfig {
+    pub fn api_base(&self) -> &str {
+        self.api_base.as_deref().unwrap_or(DEFAULT_API_BASE).trim_end_matches('/')
+    }
+
+    pub fn build_http_client(&self) -> Result<reqwest::Client, Box<dyn Error>> {
+        let mut client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(self.accept_invalid_certificates);
+
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(Duration::from_secs(timeout));
+        }
+
+        if let Some(ProxyConfig { url, username, password }) = &self.proxy {
+            let mut proxy = Proxy::all(url)?;
+            if let Some(username) = username {
+                proxy = proxy.basic_auth(username, password.as_deref().unwrap_or(""));
+            }
+            client = client.proxy(proxy);
+        }
+
+        for path in &self.root_certificates {
+            let pem = fs::read(path)?;
+            client = client.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+
+        Ok(client.build()?)
+    }
+
+    /// Builds the OpenAI client on top of an HTTP client from `build_http_client`.
+    pub fn build_openai_client(&self, http: reqwest::Client, api_key: &str) -> Client {
+        let mut client = Client::new()
+            .with_http_client(http)
+            .with_api_key(api_key)
+            .with_api_base(self.api_base());
+        if let Some(organization) = &self.organization {
+            client = client.with_org_id(organization);
+        }
+        client
+    }
+
+    /// These settings with the proxy password left out, so they're safe to show.
+    pub fn redacted(&self) -> Self {
+        let mut settings = self.clone();
+        if let Some(proxy) = &mut settings.proxy {
+            proxy.password = proxy.password.as_ref().map(|_| "********".to_string());
+        }
+        settings
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIErrorResponse {
+    error: ApiError
+}
+
+impl ChatGPTData {
+    /// Sends a request to the OpenAI API directly, rather than through the OpenAI client, which doesn't
+    /// expose response headers. This lets the throttle read the `x-ratelimit-*` headers of every response.
+    /// Like the OpenAI client, rate limits (other than a used-up quota) and server errors are retried
+    /// with exponential backoff. Errors are the OpenAI client's own, so they're handled the same way.
+    pub async fn post_openai<T: Serialize, R: DeserializeOwned>(&self, path: &str, body: &T) -> Result<R, OpenAIError> {
+        let mut attempt = 1;
+        loop {
+            let mut request = self.http
+                .post(format!("{}/{path}", self.client_settings.api_base()))
+                .bearer_auth(&self.api_key);
+            if let Some(organization) = &self.client_settings.organization {
+                request = request.header("OpenAI-Organization", organization);
+            }
+
+            let response = request.json(body).send().await.map_err(OpenAIError::Reqwest)?;
+            self.throttle.record_headers(response.headers());
+
+            let status = response.status();
+            let bytes = response.bytes().await.map_err(OpenAIError::Reqwest)?;
+            if status.is_success() {
+                return serde_json::from_slice(&bytes).map_err(OpenAIError::JSONDeserialize);
+            }
+
+            let err = match serde_json::from_slice::<OpenAIErrorResponse>(&bytes) {
+                Ok(response) => OpenAIError::ApiError(response.error),
+                Err(err) => OpenAIError::JSONDeserialize(err)
+            };
+            let out_of_quota = matches!(&err, OpenAIError::ApiError(err) if err.r#type == "insufficient_quota");
+            let retryable = (status.as_u16() == 429 && !out_of_quota) || status.is_server_error();
+            if !retryable || attempt >= MAX_ATTEMPTS {
+                return Err(err);
+            }
+
+            self.throttle.record_error(&err);
+            let backoff = Duration::from_millis(500)
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(MAX_BACKOFF);
+            println!("{}: OpenAI responded with {}, retrying in {:?} ({} / {}).", "Warning".yellow(), status, backoff, attempt, MAX_ATTEMPTS - 1);
+            sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+}
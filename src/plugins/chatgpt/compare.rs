@@ -0,0 +1,115 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke, diff_lines};
+
+use super::{ChatGPTMessage, ChatGPTRole, EphemeralRequest, EphemeralBatchRequest, BatchResponse, ResponseSettings};
+
+/// How many models are asked at once.
+const COMPARE_CONCURRENCY: usize = 4;
+
+pub async fn diff_chatgpt(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("diff_chatgpt", &[ "prompt", "first_settings", "second_settings" ], &args);
+    let prompt = args.require_string("prompt")?;
+    let first: ResponseSettings = args.require_as("first_settings", "settings")?;
+    let second: ResponseSettings = args.require_as("second_settings", "settings")?;
+
+    let messages = vec![
+        ChatGPTMessage {
+            role: ChatGPTRole::User,
+            content: prompt
+        }
+    ];
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let first = invoke::<String>(chatgpt_info, "respond ephemeral", EphemeralRequest {
+        messages: messages.clone(),
+        settings: first
+    }).await?;
+    let second = invoke::<String>(chatgpt_info, "respond ephemeral", EphemeralRequest {
+        messages,
+        settings: second
+    }).await?;
+
+    let diff = diff_lines(&first, &second).iter()
+        .map(|el| el.to_marked().into())
+        .collect::<Vec<ScriptValue>>();
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("first".to_string(), first.into()),
+        ("second".to_string(), second.into()),
+        ("diff".to_string(), diff.into())
+    ])))
+}
+
+pub struct DiffChatGPTImpl;
+
+#[async_trait]
+impl CommandImpl for DiffChatGPTImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        diff_chatgpt(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub async fn compare_models(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("compare_models", &[ "prompt", "models" ], &args);
+    let prompt = args.require_string("prompt")?;
+    let models: Vec<String> = args.require_as("models", "a list of model names")?;
+
+    let requests = models.iter()
+        .map(|model| EphemeralRequest {
+            messages: vec![
+                ChatGPTMessage {
+                    role: ChatGPTRole::User,
+                    content: prompt.clone()
+                }
+            ],
+            settings: ResponseSettings {
+                model: Some(model.clone()),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let concurrency = ctx.limit_concurrency(COMPARE_CONCURRENCY);
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let responses = invoke::<Vec<BatchResponse>>(chatgpt_info, "respond ephemeral batch with usage", EphemeralBatchRequest {
+        requests,
+        concurrency
+    }).await?;
+
+    let results = models.into_iter()
+        .zip(responses)
+        .map(|(model, response)| {
+            let (key, value) = match response.result {
+                Ok(output) => ("output", output),
+                Err(err) => ("error", err)
+            };
+
+            (model, ScriptValue::Dict(HashMap::from_iter([
+                (key.to_string(), value.into()),
+                ("tokens".to_string(), (response.tokens as i64).into())
+            ])))
+        })
+        .collect::<HashMap<_, _>>();
+
+    Ok(ScriptValue::Dict(results))
+}
+
+pub struct CompareModelsImpl;
+
+#[async_trait]
+impl CommandImpl for CompareModelsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        compare_models(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,70 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
+use tiktoken_rs::async_openai::num_tokens_from_messages;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::ChatGPTMessage;
+
+#[derive(Debug, Clone)]
+pub struct UnknownContextLimitError(pub String);
+
+impl Display for UnknownContextLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the context limit of the model '{}' is unknown; add it to the ChatGPT plugin's 'context limits'.", self.0)
+    }
+}
+
+impl Error for UnknownContextLimitError {}
+
+pub fn default_context_limits() -> HashMap<String, usize> {
+    HashMap::from_iter([
+        ("gpt-3.5-turbo".to_string(), 4096),
+        ("gpt-3.5-turbo-16k".to_string(), 16384),
+        ("gpt-4".to_string(), 8192),
+        ("gpt-4-32k".to_string(), 32768)
+    ])
+}
+
+pub async fn check_context_fit(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("check_context_fit", &[ "model", "completion_tokens", "messages" ], &args);
+    let model = args.require_string("model")?;
+    let completion_tokens = args.require_int("completion_tokens")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let messages: Vec<ChatGPTMessage> = match args.optional_as("messages", "a list of messages")? {
+        Some(messages) => messages,
+        None => invoke::<Vec<ChatGPTMessage>>(chatgpt_info, "get", true).await?
+    };
+    let context_limit = invoke::<Option<usize>>(chatgpt_info, "get context limit", &model).await?
+        .ok_or(UnknownContextLimitError(model.clone()))?;
+
+    let messages: Vec<ChatCompletionRequestMessage> = messages.into_iter()
+        .map(|el| el.into())
+        .collect();
+    let prompt_tokens = num_tokens_from_messages(&model, &messages)? as i64;
+    let headroom = context_limit as i64 - prompt_tokens - completion_tokens;
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("fits".to_string(), (headroom >= 0).into()),
+        ("prompt_tokens".to_string(), prompt_tokens.into()),
+        ("completion_tokens".to_string(), completion_tokens.into()),
+        ("context_limit".to_string(), (context_limit as i64).into()),
+        ("headroom".to_string(), headroom.into())
+    ])))
+}
+
+pub struct CheckContextFitImpl;
+
+#[async_trait]
+impl CommandImpl for CheckContextFitImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        check_context_fit(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
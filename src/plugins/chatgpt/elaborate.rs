@@ -0,0 +1,62 @@
+use std::{error::Error, fmt::Display};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::ask_chatgpt_ephemeral;
+
+const ELABORATE_PROMPT: &str = r#"You expand short answers. Rewrite the answer the user sends you with more detail, explanation, and concrete examples, keeping everything it already says correct and in the same order.
+
+Respond with only the expanded answer."#;
+
+#[derive(Debug, Clone)]
+pub struct SaveGivenTextError;
+
+impl Display for SaveGivenTextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'save' only works when elaborating ChatGPT's last response; leave out 'text' to save.")
+    }
+}
+
+impl Error for SaveGivenTextError {}
+
+pub async fn elaborate(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("elaborate", &[ "text", "save" ], &args);
+    let text = args.optional_string("text")?;
+    let save = args.optional_bool("save")?.unwrap_or(false);
+
+    // Saving replaces ChatGPT's last response, so it must be the one that was expanded.
+    if save && text.is_some() {
+        return Err(Box::new(SaveGivenTextError));
+    }
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let text = match text {
+        Some(text) => text,
+        None => invoke::<String>(chatgpt_info, "last", true).await?
+    };
+
+    let expanded = ask_chatgpt_ephemeral(ctx, ELABORATE_PROMPT, &text).await?;
+    let expanded = expanded.trim().to_string();
+
+    if save {
+        let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+        invoke::<bool>(chatgpt_info, "replace last", &expanded).await?;
+    }
+
+    Ok(expanded.into())
+}
+
+pub struct ElaborateImpl;
+
+#[async_trait]
+impl CommandImpl for ElaborateImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        elaborate(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+
+use async_openai::types::{CreateEmbeddingRequest, CreateEmbeddingResponse, EmbeddingInput};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::ChatGPTData;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EmbedRequest {
+    pub texts: Vec<String>,
+    #[serde(default)] pub model: Option<String>
+}
+
+impl ChatGPTData {
+    /// Embeds every text in one request. Returns the embeddings in the same order as the texts, and the tokens used.
+    pub async fn embed(&self, request: EmbedRequest) -> Result<(Vec<Vec<f32>>, usize), Box<dyn Error>> {
+        if request.texts.len() == 0 {
+            return Ok((vec![], 0));
+        }
+
+        self.throttle.wait().await;
+        let response = self.post_openai::<_, CreateEmbeddingResponse>("embeddings", &CreateEmbeddingRequest {
+            model: request.model.unwrap_or(self.embedding_model.clone()),
+            user: self.user.clone(),
+            input: EmbeddingInput::StringArray(request.texts)
+        }).await;
+        if let Err(err) = &response {
+            self.throttle.record_error(err);
+        }
+        let response = response?;
+
+        let mut data = response.data;
+        data.sort_by_key(|el| el.index);
+
+        Ok((data.into_iter().map(|el| el.embedding).collect(), response.usage.total_tokens as usize))
+    }
+}
+
+fn embedding_to_value(embedding: Vec<f32>) -> ScriptValue {
+    ScriptValue::List(embedding.into_iter().map(|el| ScriptValue::Float(el as f64)).collect())
+}
+
+pub async fn embed(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("embed", &[ "text", "model" ], &args);
+    let model = args.optional_string("model")?;
+
+    // A single text gets a single embedding back; a list gets a list of them.
+    let (texts, single) = match args.require("text")? {
+        ScriptValue::String(text) => (vec![ text.clone() ], true),
+        _ => (args.require_as::<Vec<String>>("text", "a string or a list of strings")?, false)
+    };
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let mut embeddings = invoke::<Vec<Vec<f32>>>(chatgpt_info, "embed", EmbedRequest { texts, model }).await?;
+
+    Ok(match single {
+        true => embedding_to_value(embeddings.remove(0)),
+        false => ScriptValue::List(embeddings.into_iter().map(embedding_to_value).collect())
+    })
+}
+
+pub struct EmbedImpl;
+
+#[async_trait]
+impl CommandImpl for EmbedImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        embed(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,77 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue};
+
+use super::{ask_chatgpt_ephemeral, parse_model_json};
+
+const ENTITIES_PROMPT: &str = r#"You extract named entities from text. Find every person, organization, location, date, and other notable named entity in the text the user sends you.
+
+Copy each entity's text exactly as it appears. Do not include an entity more than once.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "entities": [
+        { "text": "...", "type": "person" | "organization" | "location" | "date" | "other" }
+    ]
+}"#;
+
+#[derive(Serialize, Deserialize)]
+pub struct Entity {
+    pub text: String,
+    #[serde(rename = "type")] pub entity_type: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EntitiesResponse {
+    pub entities: Vec<Entity>
+}
+
+/// Finds the character span of `entity` in `text`. The model's own offsets are unreliable, so they are never asked for.
+fn find_span(text: &str, entity: &str) -> Option<(usize, usize)> {
+    let start = text.find(entity)?;
+    let start = text[..start].chars().count();
+    Some((start, start + entity.chars().count()))
+}
+
+pub async fn extract_entities(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("extract_entities", &[ "text" ], &args);
+    let text = args.require_string("text")?;
+
+    let response = ask_chatgpt_ephemeral(ctx, ENTITIES_PROMPT, &text).await?;
+    let response: EntitiesResponse = parse_model_json(ctx, &response).await?;
+
+    let entities = response.entities.into_iter()
+        .map(|entity| {
+            let (start, end) = match find_span(&text, &entity.text) {
+                Some((start, end)) => ((start as i64).into(), (end as i64).into()),
+                None => (ScriptValue::None, ScriptValue::None)
+            };
+
+            ScriptValue::Dict(HashMap::from_iter([
+                ("text".to_string(), entity.text.into()),
+                ("type".to_string(), entity.entity_type.into()),
+                ("start".to_string(), start),
+                ("end".to_string(), end)
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(entities.into())
+}
+
+pub struct ExtractEntitiesImpl;
+
+#[async_trait]
+impl CommandImpl for ExtractEntitiesImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        extract_entities(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,77 @@
+use std::error::Error;
+
+use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ChatGPTData, ChatGPTMessage, ChatGPTRole, ResponseSettings, parse_model_json};
+
+const DEFAULT_FOLLOW_UPS: usize = 3;
+const MAX_FOLLOW_UPS: usize = 10;
+
+const FOLLOW_UPS_PROMPT: &str = r#"You suggest follow-up questions. You will be given a conversation between a user and an assistant. Suggest questions the user could ask next to explore the subject further. Each question should be specific to the conversation, and not already answered in it.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "questions": [ "...", "..." ]
+}"#;
+
+#[derive(Serialize, Deserialize)]
+pub struct FollowUpsResponse {
+    pub questions: Vec<String>
+}
+
+impl ChatGPTData {
+    /// Asks for follow-up questions to the conversation in memory, without changing it.
+    /// Returns `None` if there's no conversation yet.
+    pub async fn generate_follow_ups(&mut self, count: usize) -> Result<Option<String>, Box<dyn Error>> {
+        let transcript = self.transcript();
+
+        if transcript.len() == 0 {
+            return Ok(None);
+        }
+
+        let messages: Vec<ChatCompletionRequestMessage> = vec![
+            ChatGPTMessage { role: ChatGPTRole::System, content: FOLLOW_UPS_PROMPT.to_string() }.into(),
+            ChatGPTMessage { role: ChatGPTRole::User, content: format!("Suggest {count} follow-up questions to this conversation:\n\n{transcript}") }.into()
+        ];
+        Ok(Some(self.respond(messages, ResponseSettings::default()).await?))
+    }
+}
+
+pub async fn follow_ups(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("follow_ups", &[ "count" ], &args);
+    let count = args.optional_int("count")?
+        .map(|el| (el.max(1) as usize).min(MAX_FOLLOW_UPS))
+        .unwrap_or(DEFAULT_FOLLOW_UPS);
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let Some(response) = invoke::<Option<String>>(chatgpt_info, "follow ups", count).await? else {
+        return Ok(ScriptValue::List(vec![]));
+    };
+    let response: FollowUpsResponse = parse_model_json(ctx, &response).await?;
+
+    let questions = response.questions.into_iter()
+        .map(|el| el.trim().to_string())
+        .filter(|el| !el.is_empty())
+        .take(count)
+        .map(|el| el.into())
+        .collect::<Vec<ScriptValue>>();
+    Ok(questions.into())
+}
+
+pub struct FollowUpsImpl;
+
+#[async_trait]
+impl CommandImpl for FollowUpsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        follow_ups(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
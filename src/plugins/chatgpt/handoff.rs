@@ -0,0 +1,52 @@
+use std::error::Error;
+
+use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, ScriptValue, invoke};
+
+use super::{ask_chatgpt_ephemeral, transcript, ChatGPTMessage};
+
+const HANDOFF_PROMPT: &str = r#"You write handoff notes. You will be given a conversation between a user and an assistant. Summarize its current state for someone who will continue the work without reading it.
+
+Respond in exactly this format:
+
+Goals:
+- ...
+
+Decisions:
+- ...
+
+Open questions:
+- ...
+
+Keep each point short. Write "- None" under a heading if nothing fits."#;
+
+pub async fn handoff_note(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let memory = invoke::<Vec<ChatGPTMessage>>(chatgpt_info, "get", true).await?;
+    let memory = memory.into_iter().map(ChatCompletionRequestMessage::from).collect::<Vec<_>>();
+
+    let transcript = transcript(&memory);
+
+    if transcript.len() == 0 {
+        return Ok("There is no conversation to summarize yet.".to_string().into());
+    }
+
+    let response = ask_chatgpt_ephemeral(ctx, HANDOFF_PROMPT, &transcript).await?;
+
+    Ok(response.trim().to_string().into())
+}
+
+pub struct HandoffNoteImpl;
+
+#[async_trait]
+impl CommandImpl for HandoffNoteImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        handoff_note(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
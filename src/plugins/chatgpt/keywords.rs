@@ -0,0 +1,95 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ask_chatgpt_ephemeral, parse_model_json};
+
+const KEYWORDS_PROMPT: &str = r#"You extract keywords from text. Find the most salient keywords and short key phrases in the text the user sends you, most important first.
+
+Prefer specific phrases over generic words. Do not include a keyword more than once.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "keywords": [ "...", "..." ]
+}"#;
+
+#[derive(Serialize, Deserialize)]
+pub struct KeywordsResponse {
+    pub keywords: Vec<String>
+}
+
+/// Lowercases a keyword, except for the words in it written in all capitals, like "NASA" or "GPU".
+pub fn normalize_keyword(keyword: &str) -> String {
+    keyword.split_whitespace()
+        .map(|word| {
+            let letters = word.chars().filter(|el| el.is_alphabetic()).count();
+            let is_acronym = letters > 1 && word.chars().filter(|el| el.is_alphabetic()).all(|el| el.is_uppercase());
+            match is_acronym {
+                true => word.to_string(),
+                false => word.to_lowercase()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|el: char| el.is_ascii_punctuation())
+        .to_string()
+}
+
+/// Keeps the first of any keywords that are the same ignoring case, in the model's order.
+pub fn dedupe_keywords(keywords: Vec<String>, max: usize) -> Vec<String> {
+    let mut seen: Vec<String> = vec![];
+    let mut deduped = vec![];
+
+    for keyword in keywords.iter().map(|el| normalize_keyword(el)) {
+        let key = keyword.to_lowercase();
+        if keyword.is_empty() || seen.contains(&key) {
+            continue;
+        }
+
+        seen.push(key);
+        deduped.push(keyword);
+        if deduped.len() >= max {
+            break;
+        }
+    }
+
+    deduped
+}
+
+pub async fn keywords(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("keywords", &[ "text", "max" ], &args);
+    let text = args.require_string("text")?;
+    let max = match args.optional_int("max")? {
+        Some(max) => max.max(1) as usize,
+        None => {
+            let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+            invoke::<usize>(chatgpt_info, "get max keywords", true).await?
+        }
+    };
+
+    let query = format!("Find at most {max} keywords in this text:\n\n{text}");
+    let response = ask_chatgpt_ephemeral(ctx, KEYWORDS_PROMPT, &query).await?;
+    let response: KeywordsResponse = parse_model_json(ctx, &response).await?;
+
+    let keywords = dedupe_keywords(response.keywords, max).into_iter()
+        .map(|el| el.into())
+        .collect::<Vec<ScriptValue>>();
+    Ok(keywords.into())
+}
+
+pub struct KeywordsImpl;
+
+#[async_trait]
+impl CommandImpl for KeywordsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        keywords(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
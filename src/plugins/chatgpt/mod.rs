@@ -1,24 +1,292 @@
-use std::{error::Error, fmt::Display, collections::HashMap, process::CommandArgs};
+use std::{error::Error, fmt::Display, collections::HashMap, time::{Duration, Instant}, fs::OpenOptions, io::Write};
 
-use async_openai::{types::{CreateChatCompletionRequest, CreateChatCompletionResponse, ChatCompletionRequestMessage, Role}, error::OpenAIError, Client};
+use async_openai::{types::{CreateChatCompletionRequest, CreateChatCompletionResponse, ChatCompletionRequestMessage, Role}, error::{OpenAIError, ApiError}, Client};
 use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use colored::Colorize;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Value, json};
 
-use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, CommandNoArgError, PluginData, PluginDataNoInvoke, invoke, PluginCycle, ScriptValue, CommandArgument};
+use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, CommandArgs, PluginData, PluginDataNoInvoke, invoke, PluginCycle, ScriptValue, CommandArgument, check_config, render_template, template_placeholders, AgentInfo, Weights};
+
+mod translate;
+mod compare;
+mod context;
+mod handoff;
+mod classify;
+mod entities;
+mod plan;
+mod vision;
+mod client;
+mod title;
+mod elaborate;
+mod sessions;
+mod calc;
+mod throttle;
+mod embed;
+mod repair;
+mod packing;
+mod trim;
+mod schema;
+mod checkpoint;
+mod keywords;
+mod followups;
+
+pub use translate::*;
+pub use compare::*;
+pub use context::*;
+pub use handoff::*;
+pub use classify::*;
+pub use entities::*;
+pub use plan::*;
+pub use vision::*;
+pub use client::*;
+pub use title::*;
+pub use elaborate::*;
+pub use sessions::*;
+pub use calc::*;
+pub use throttle::*;
+pub use embed::*;
+pub use repair::*;
+pub use packing::*;
+pub use trim::*;
+pub use schema::*;
+pub use checkpoint::*;
+pub use keywords::*;
+pub use followups::*;
 
 const CHAT_GPT_PROMPT: &str = r#"You are ChatGPT, a large language model trained by OpenAI, based on the GPT-3.5 architecture. As an assistant, your purpose is to provide helpful and informative responses to a wide variety of questions and topics, while also engaging in natural and friendly conversation with users.
 
 As ChatGPT, you must always prioritize safety and appropriate behavior in all interactions. This means that you are programmed to avoid any content that could be harmful or offensive, and to always maintain a respectful and polite tone."#;
 
+#[derive(Debug, Clone)]
+pub struct ChatGPTAuthError(pub String);
+
+impl Display for ChatGPTAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the ChatGPT plugin could not authenticate with OpenAI; check its 'api key'. ({})", self.0)
+    }
+}
+
+impl Error for ChatGPTAuthError {}
+
+#[derive(Debug, Clone)]
+pub struct UnusedPromptVariablesError(pub Vec<String>);
+
+impl Display for UnusedPromptVariablesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the ChatGPT plugin's 'variables' include {}, which the 'system prompt' never uses.", self.0.join(", "))
+    }
+}
+
+impl Error for UnusedPromptVariablesError {}
+
+#[derive(Debug, Clone)]
+pub struct ContentFilteredError(pub String);
+
+impl Display for ContentFilteredError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' refused to respond because of OpenAI's content filter.", self.0)
+    }
+}
+
+impl Error for ContentFilteredError {}
+
+#[derive(Debug, Clone)]
+pub struct EmptyResponseError(pub String, pub Option<String>);
+
+impl Display for EmptyResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' returned an empty response", self.0)?;
+        if let Some(finish_reason) = &self.1 {
+            write!(f, " (finish reason: {finish_reason})")?;
+        }
+        write!(f, ".")
+    }
+}
+
+impl Error for EmptyResponseError {}
+
+#[derive(Debug, Clone)]
+pub struct NoAssistantMessageError;
+
+impl Display for NoAssistantMessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChatGPT has not responded yet, so there is no last response.")
+    }
+}
+
+impl Error for NoAssistantMessageError {}
+
+#[derive(Debug, Clone)]
+pub struct NoModelsError;
+
+impl Display for NoModelsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the ChatGPT plugin's 'models' list is empty.")
+    }
+}
+
+impl Error for NoModelsError {}
+
 pub struct ChatGPTData {
     pub client: Client,
-    pub memory: Vec<ChatCompletionRequestMessage>
+    pub http: reqwest::Client,
+    pub api_key: String,
+    pub vision_model: String,
+    pub client_settings: OpenAIClientConfig,
+    pub max_title_length: usize,
+    pub max_keywords: usize,
+    pub throttle: Throttle,
+    pub idle_timeout: Option<Duration>,
+    pub last_activity: Option<Instant>,
+    pub embedding_model: String,
+    pub blocklist: Vec<Regex>,
+    pub blocklist_refusal: String,
+    pub system_prompt: String,
+    pub context_limits: HashMap<String, usize>,
+    pub user: Option<String>,
+    pub memory: Vec<ChatCompletionRequestMessage>,
+    pub sessions: HashMap<String, Vec<ChatCompletionRequestMessage>>,
+    pub response_filters: Vec<(Regex, String)>,
+    pub unreported_tokens: usize,
+    pub models: Vec<String>,
+    pub fine_tuning_path: String,
+    pub dedupe_system_messages: bool,
+    pub json_repair: bool,
+    pub packing: PackingConfig,
+    pub schemas: HashMap<String, NamedSchema>,
+    pub checkpoints: Checkpoints
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ChatGPTPluginConfig {
-    #[serde(rename = "api key")] pub api_key: String
+    #[serde(rename = "api key")] pub api_key: String,
+    #[serde(rename = "response filters", default)] pub response_filters: Vec<ResponseFilter>,
+    #[serde(rename = "system prompt", default)] pub system_prompt: Option<String>,
+    #[serde(default)] pub variables: HashMap<String, String>,
+    #[serde(rename = "context limits", default)] pub context_limits: HashMap<String, usize>,
+    #[serde(default)] pub user: Option<String>,
+    #[serde(default = "default_models")] pub models: Vec<String>,
+    #[serde(rename = "fine-tuning path", default = "default_fine_tuning_path")] pub fine_tuning_path: String,
+    #[serde(rename = "dedupe system messages", default = "default_dedupe_system_messages")] pub dedupe_system_messages: bool,
+    #[serde(rename = "response language", default)] pub response_language: Option<String>,
+    #[serde(rename = "vision model", default = "default_vision_model")] pub vision_model: String,
+    #[serde(default)] pub client: OpenAIClientConfig,
+    #[serde(rename = "max title length", default = "default_max_title_length")] pub max_title_length: usize,
+    #[serde(rename = "max keywords", default = "default_max_keywords")] pub max_keywords: usize,
+    #[serde(default)] pub throttle: ThrottleConfig,
+    #[serde(rename = "idle timeout", default)] pub idle_timeout: Option<u64>,
+    #[serde(rename = "embedding model", default = "default_embedding_model")] pub embedding_model: String,
+    /// Keywords that a prompt is refused for, without asking OpenAI. Entries starting with 'regex:' are regexes.
+    #[serde(default)] pub blocklist: Vec<String>,
+    #[serde(rename = "blocklist refusal", default = "default_blocklist_refusal")] pub blocklist_refusal: String,
+    #[serde(rename = "json repair", default = "default_json_repair")] pub json_repair: bool,
+    #[serde(default)] pub packing: PackingConfig,
+    /// JSON schemas that `ask_chatgpt_json` can be given by name.
+    #[serde(default)] pub schemas: HashMap<String, Value>,
+    /// Periodic snapshots of memory on disk, to recover a conversation after a crash.
+    #[serde(default)] pub checkpoints: CheckpointConfig
+}
+
+fn default_json_repair() -> bool {
+    true
+}
+
+fn default_blocklist_refusal() -> String {
+    "I can't help with that request.".to_string()
+}
+
+/// Compiles the blocklist case-insensitively. Keywords match literally, so ones like 'c++' work as written.
+fn compile_blocklist(blocklist: &[String]) -> Result<Vec<Regex>, regex::Error> {
+    blocklist.iter()
+        .map(|el| match el.strip_prefix("regex:") {
+            Some(pattern) => Regex::new(&format!("(?i){pattern}")),
+            None => Regex::new(&format!("(?i){}", regex::escape(el)))
+        })
+        .collect()
+}
+
+fn default_embedding_model() -> String {
+    "text-embedding-ada-002".to_string()
+}
+
+fn default_max_title_length() -> usize {
+    60
+}
+
+fn default_max_keywords() -> usize {
+    10
+}
+
+fn default_vision_model() -> String {
+    "gpt-4-vision-preview".to_string()
+}
+
+fn default_dedupe_system_messages() -> bool {
+    true
+}
+
+fn default_fine_tuning_path() -> String {
+    "fine_tuning.jsonl".to_string()
+}
+
+fn default_models() -> Vec<String> {
+    vec![ "gpt-3.5-turbo".to_string() ]
+}
+
+/// Whether an error is the provider being overloaded or down, rather than something wrong with the request.
+pub fn is_model_unavailable(err: &OpenAIError) -> bool {
+    match err {
+        OpenAIError::ApiError(err) => {
+            let message = err.message.to_lowercase();
+            err.r#type == "server_error" || message.contains("overloaded") || message.contains("unavailable")
+        }
+        OpenAIError::Reqwest(err) => err.is_timeout() || err.is_connect(),
+        _ => false
+    }
+}
+
+/// Whether OpenAI rejected the API key itself. Checked by the error code, since messages can mention
+/// API keys for other reasons.
+pub fn is_invalid_api_key(err: &ApiError) -> bool {
+    err.code.as_ref().and_then(|el| el.as_str()) == Some("invalid_api_key")
+}
+
+impl ChatGPTPluginConfig {
+    pub fn render_system_prompt(&self) -> Result<String, Box<dyn Error>> {
+        let mut prompt = self.render_base_system_prompt()?;
+        if let Some(language) = &self.response_language {
+            prompt.push_str(&format!("\n\nAlways respond in {language}, no matter what language you are asked in."));
+        }
+        Ok(prompt)
+    }
+
+    fn render_base_system_prompt(&self) -> Result<String, Box<dyn Error>> {
+        let template = match &self.system_prompt {
+            Some(system_prompt) => system_prompt,
+            None => return Ok(CHAT_GPT_PROMPT.to_string())
+        };
+
+        let placeholders = template_placeholders(template);
+        let mut unused = self.variables.keys()
+            .filter(|el| !placeholders.contains(*el))
+            .map(|el| format!("\"{el}\""))
+            .collect::<Vec<_>>();
+        if unused.len() > 0 {
+            unused.sort();
+            return Err(Box::new(UnusedPromptVariablesError(unused)));
+        }
+
+        Ok(render_template(template, &self.variables)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ResponseFilter {
+    pub pattern: String,
+    pub replacement: String
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
@@ -69,8 +337,139 @@ impl From<ChatCompletionRequestMessage> for ChatGPTMessage {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ChatGPTMessage {
-    role: ChatGPTRole,
-    content: String
+    pub role: ChatGPTRole,
+    pub content: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ResponseSettings {
+    #[serde(default)] pub model: Option<String>,
+    #[serde(default)] pub temperature: Option<f32>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EphemeralRequest {
+    pub messages: Vec<ChatGPTMessage>,
+    #[serde(default)] pub settings: ResponseSettings
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EphemeralBatchRequest {
+    pub requests: Vec<EphemeralRequest>,
+    pub concurrency: usize
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchResponse {
+    pub result: Result<String, String>,
+    pub tokens: usize
+}
+
+/// Writes out the user and assistant turns of a conversation as "User: ..." and "Assistant: ...",
+/// leaving out system messages. Empty if the conversation has no turns yet.
+pub fn transcript(messages: &[ChatCompletionRequestMessage]) -> String {
+    messages.iter()
+        .filter_map(|el| match el.role {
+            Role::User => Some(format!("User: {}", el.content)),
+            Role::Assistant => Some(format!("Assistant: {}", el.content)),
+            Role::System => None
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+impl ChatGPTData {
+    /// The transcript of the conversation in memory.
+    pub fn transcript(&self) -> String {
+        transcript(&self.memory)
+    }
+
+    pub async fn respond(&mut self, messages: Vec<ChatCompletionRequestMessage>, settings: ResponseSettings) -> Result<String, Box<dyn Error>> {
+        let (content, tokens) = self.respond_with_usage(messages, settings).await?;
+        self.unreported_tokens += tokens;
+        Ok(content)
+    }
+
+    /// Responds without recording usage, so that several responses can be made at once. Returns the tokens used.
+    pub async fn respond_with_usage(&self, messages: Vec<ChatCompletionRequestMessage>, settings: ResponseSettings) -> Result<(String, usize), Box<dyn Error>> {
+        let mut request = CreateChatCompletionRequest::default();
+
+        request.messages = messages;
+        request.temperature = settings.temperature;
+        request.user = self.user.clone();
+
+        // A model asked for by name is used alone; otherwise the configured models are tried in order.
+        let models = match settings.model {
+            Some(model) => vec![ model ],
+            None => self.models.clone()
+        };
+
+        let mut result = None;
+        for (ind, model) in models.iter().enumerate() {
+            request.model = model.clone();
+
+            self.throttle.wait().await;
+            let response = self.post_openai::<_, CreateChatCompletionResponse>("chat/completions", &request).await;
+            if let Err(err) = &response {
+                self.throttle.record_error(err);
+            }
+
+            match response {
+                Ok(response) => {
+                    result = Some((model.clone(), response));
+                    break;
+                }
+                Err(err) if is_model_unavailable(&err) && ind + 1 < models.len() => {
+                    println!("{}: '{}' is unavailable, falling back to '{}': {}", "Warning".yellow(), model, models[ind + 1], err);
+                }
+                Err(err) => return Err(Box::new(err))
+            }
+        }
+        let (model, response): (String, CreateChatCompletionResponse) = result.ok_or(NoModelsError)?;
+
+        let tokens = response.usage.as_ref()
+            .map(|el| el.total_tokens as usize)
+            .unwrap_or(0);
+
+        // A filtered response comes back as a normal one, so it has to be told apart by its finish reason.
+        let choice = response.choices.get(0).ok_or(EmptyResponseError(model.clone(), None))?;
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(Box::new(ContentFilteredError(model)));
+        }
+        if choice.message.content.trim().is_empty() {
+            return Err(Box::new(EmptyResponseError(model, choice.finish_reason.clone())));
+        }
+
+        let mut content = choice.message.content.clone();
+        for (pattern, replacement) in &self.response_filters {
+            content = pattern.replace_all(&content, replacement.as_str()).to_string();
+        }
+
+        Ok((content, tokens))
+    }
+
+    /// Responds to several requests at once, in order. A request that fails doesn't stop the others.
+    pub async fn respond_batch(&mut self, request: EphemeralBatchRequest) -> Vec<BatchResponse> {
+        let EphemeralBatchRequest { requests, concurrency } = request;
+
+        let data = &*self;
+        let responses: Vec<BatchResponse> = stream::iter(requests)
+            .map(|EphemeralRequest { messages, settings }| async move {
+                let messages = messages.into_iter()
+                    .map(|el| el.into())
+                    .collect::<Vec<_>>();
+                match data.respond_with_usage(messages, settings).await {
+                    Ok((content, tokens)) => BatchResponse { result: Ok(content), tokens },
+                    Err(err) => BatchResponse { result: Err(err.to_string()), tokens: 0 }
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+
+        self.unreported_tokens += responses.iter().map(|el| el.tokens).sum::<usize>();
+        responses
+    }
 }
 
 #[async_trait]
@@ -83,6 +482,15 @@ impl PluginData for ChatGPTData {
             "push" => {
                 let ChatGPTMessage { role, content } = serde_json::from_value(value)?;
 
+                // Pushing the same system prompt twice in a row only wastes tokens, so the repeat is dropped.
+                if self.dedupe_system_messages {
+                    if let (ChatGPTRole::System, Some(last)) = (role, self.memory.last()) {
+                        if last.role == Role::System && last.content == content {
+                            return Ok(true.into());
+                        }
+                    }
+                }
+
                 self.memory.push(
                     ChatCompletionRequestMessage {
                         role: role.into(),
@@ -93,26 +501,212 @@ impl PluginData for ChatGPTData {
 
                 Ok(true.into())
             }
+            "save session" => {
+                let name: String = serde_json::from_value(value)?;
+                self.sessions.insert(name, self.memory.clone());
+                Ok(self.memory.len().into())
+            }
+            "list checkpoints" => {
+                Ok(serde_json::to_value(self.list_checkpoints()?)?)
+            }
+            "load checkpoint" => {
+                let name: String = serde_json::from_value(value)?;
+                Ok(self.load_checkpoint(&name)?.into())
+            }
+            "delete checkpoint" => {
+                let name: String = serde_json::from_value(value)?;
+                self.delete_checkpoint(&name)?;
+                Ok(true.into())
+            }
+            "merge sessions" => {
+                let request: MergeSessionsRequest = serde_json::from_value(value)?;
+                Ok(self.merge_sessions(request).await?.into())
+            }
+            "check blocklist" => {
+                let query: String = serde_json::from_value(value)?;
+                match self.blocklist.iter().find(|el| el.is_match(&query)) {
+                    Some(pattern) => {
+                        // The pattern's (?i) prefix is only an implementation detail, so it's left out of the log.
+                        println!("{}: Refused a ChatGPT prompt matching the blocklist entry '{}'.", "Warning".yellow(), pattern.as_str().trim_start_matches("(?i)"));
+                        Ok(self.blocklist_refusal.clone().into())
+                    }
+                    None => Ok(Value::Null)
+                }
+            }
+            "expire idle" => {
+                // Clears a conversation that has sat unused for longer than the idle timeout.
+                let expired = match (self.idle_timeout, self.last_activity) {
+                    (Some(idle_timeout), Some(last_activity)) => last_activity.elapsed() > idle_timeout,
+                    _ => false
+                };
+                if expired {
+                    self.memory.clear();
+                    self.last_activity = None;
+                }
+                Ok(expired.into())
+            }
+            "trim" => {
+                let exchanges: usize = serde_json::from_value(value)?;
+                Ok(self.trim(exchanges).into())
+            }
             "clear" => {
                 self.memory.clear();
                 Ok(true.into())
             }
             "respond" => {
-                let mut request = CreateChatCompletionRequest::default();
+                let messages = self.memory.clone();
+                let content = self.respond(messages, ResponseSettings::default()).await?;
+                self.last_activity = Some(Instant::now());
+                self.checkpoint_if_due();
 
-                let messages: Vec<ChatCompletionRequestMessage> = self.memory
-                    .iter()
-                    .map(|el| el.clone().into())
+                Ok(content.into())
+            }
+            "respond ephemeral" => {
+                let EphemeralRequest { messages, settings } = serde_json::from_value(value)?;
+                let messages: Vec<ChatCompletionRequestMessage> = messages
+                    .into_iter()
+                    .map(|el| el.into())
+                    .collect::<Vec<_>>();
+                let content = self.respond(messages, settings).await?;
+
+                Ok(content.into())
+            }
+            "respond ephemeral batch" => {
+                let request: EphemeralBatchRequest = serde_json::from_value(value)?;
+                let results = self.respond_batch(request).await.into_iter()
+                    .map(|el| el.result)
                     .collect::<Vec<_>>();
-            
-                request.model = "gpt-3.5-turbo".to_string();
-                request.messages = messages;
 
-                let response: CreateChatCompletionResponse = self.client
-                    .chat()      // Get the API "group" (completions, images, etc.) from the client
-                    .create(request.clone()).await?;
+                Ok(serde_json::to_value(results)?)
+            }
+            "respond ephemeral batch with usage" => {
+                let request: EphemeralBatchRequest = serde_json::from_value(value)?;
+                Ok(serde_json::to_value(self.respond_batch(request).await)?)
+            }
+            "embed" => {
+                let request: EmbedRequest = serde_json::from_value(value)?;
+                let (embeddings, tokens) = self.embed(request).await?;
+                self.unreported_tokens += tokens;
+                Ok(serde_json::to_value(embeddings)?)
+            }
+            "respond vision" => {
+                let request: VisionRequest = serde_json::from_value(value)?;
+                let (content, tokens) = self.respond_vision(request).await?;
+                self.unreported_tokens += tokens;
+
+                Ok(content.into())
+            }
+            "list models" => {
+                let models = self.client.models().list().await
+                    .map_err(|err| match err {
+                        OpenAIError::ApiError(err) if is_invalid_api_key(&err) => {
+                            Box::new(ChatGPTAuthError(err.message)) as Box<dyn Error>
+                        }
+                        err => Box::new(err) as Box<dyn Error>
+                    })?;
 
-                Ok(response.choices[0].message.content.clone().into())
+                let ids: Vec<Value> = models.data.iter()
+                    .map(|el| el.id.clone().into())
+                    .collect::<Vec<_>>();
+                Ok(ids.into())
+            }
+            "get context limit" => {
+                let model: String = serde_json::from_value(value)?;
+                Ok(self.context_limits.get(&model).cloned().into())
+            }
+            "set api key" => {
+                let api_key: String = serde_json::from_value(value)?;
+                self.client = self.client_settings.build_openai_client(self.http.clone(), &api_key);
+                self.api_key = api_key;
+                Ok(true.into())
+            }
+            "pack context" => {
+                let request: PackRequest = serde_json::from_value(value)?;
+                Ok(serde_json::to_value(self.pack_context(request)?)?)
+            }
+            "get recall count" => {
+                Ok(self.packing.recall_count.into())
+            }
+            "respond packed" => {
+                let recalled: Vec<String> = serde_json::from_value(value)?;
+                let packed = self.pack_context(PackRequest { recalled, messages: None, model: None })?;
+                let messages = packed.messages.into_iter().map(|el| el.into()).collect();
+                let content = self.respond(messages, ResponseSettings::default()).await?;
+                self.last_activity = Some(Instant::now());
+                self.checkpoint_if_due();
+
+                Ok(content.into())
+            }
+            "get schema" => {
+                let name: String = serde_json::from_value(value)?;
+                Ok(self.schemas.get(&name).map(|el| el.schema.clone()).into())
+            }
+            "check schema response" => {
+                let SchemaCheckRequest { name, response } = serde_json::from_value(value)?;
+                let schema = self.schemas.get(&name).ok_or_else(|| NoSchemaError(name.clone()))?;
+                Ok(serde_json::to_value(check_response(&schema.compiled, &response, self.json_repair))?)
+            }
+            "get max keywords" => {
+                Ok(self.max_keywords.into())
+            }
+            "get json repair" => {
+                Ok(self.json_repair.into())
+            }
+            "get client settings" => {
+                Ok(serde_json::to_value(self.client_settings.redacted())?)
+            }
+            "take usage" => {
+                let tokens = self.unreported_tokens;
+                self.unreported_tokens = 0;
+                Ok(tokens.into())
+            }
+            "export fine-tuning" => {
+                let messages = self.memory.iter()
+                    .map(|el| json!({
+                        "role": match el.role {
+                            Role::System => "system",
+                            Role::User => "user",
+                            Role::Assistant => "assistant"
+                        },
+                        "content": el.content
+                    }))
+                    .collect::<Vec<_>>();
+                let line = serde_json::to_string(&json!({ "messages": messages }))?;
+
+                // Each export is one training example, so it is appended as its own line.
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.fine_tuning_path)?;
+                writeln!(file, "{line}")?;
+
+                Ok(self.fine_tuning_path.clone().into())
+            }
+            "title" => {
+                Ok(self.generate_title().await?.into())
+            }
+            "follow ups" => {
+                let count: usize = serde_json::from_value(value)?;
+                Ok(self.generate_follow_ups(count).await?.into())
+            }
+            "get system prompt" => {
+                Ok(self.system_prompt.clone().into())
+            }
+            "last" => {
+                let last = self.memory.iter()
+                    .rev()
+                    .find(|el| el.role == Role::Assistant)
+                    .ok_or(NoAssistantMessageError)?;
+                Ok(last.content.clone().into())
+            }
+            "replace last" => {
+                let content: String = serde_json::from_value(value)?;
+                let last = self.memory.iter_mut()
+                    .rev()
+                    .find(|el| el.role == Role::Assistant)
+                    .ok_or(NoAssistantMessageError)?;
+                last.content = content;
+                Ok(true.into())
             }
             "get" => {
                 let gpt_messages: Vec<ChatGPTMessage> = self.memory.iter()
@@ -133,12 +727,19 @@ impl PluginData for ChatGPTData {
 pub async fn ask_chatgpt(context: &mut CommandContext, query: &str) -> Result<String, Box<dyn Error>> {
     let chatgpt_info = context.plugin_data.get_data("ChatGPT")?;
 
+    // Blocked prompts get the canned refusal, and never reach OpenAI or the conversation.
+    if let Some(refusal) = invoke::<Option<String>>(chatgpt_info, "check blocklist", query).await? {
+        return Ok(refusal);
+    }
+
+    invoke::<bool>(chatgpt_info, "expire idle", true).await?;
     let len = invoke::<usize>(chatgpt_info, "len", true).await?;
 
     if len == 0 {
+        let system_prompt = invoke::<String>(chatgpt_info, "get system prompt", true).await?;
         invoke::<bool>(chatgpt_info, "push", ChatGPTMessage {
             role: ChatGPTRole::System,
-            content: CHAT_GPT_PROMPT.to_string()
+            content: system_prompt
         }).await?;
     }
 
@@ -147,8 +748,26 @@ pub async fn ask_chatgpt(context: &mut CommandContext, query: &str) -> Result<St
         content: query.to_string()
     }).await?;
 
-    let content = invoke::<String>(chatgpt_info, "respond", true).await?;
-    
+    // With packing on, memories recalled for the prompt are packed in with as much of the
+    // conversation as fits. The conversation itself is kept whole.
+    let recall_count = invoke::<Option<usize>>(chatgpt_info, "get recall count", true).await?;
+    let content = match recall_count {
+        Some(count) => {
+            let AgentInfo { llm, observations, .. } = &mut context.agents.employee;
+            let recalled = observations.get_memories(llm, query, count, Weights {
+                recall: 1.,
+                recency: 1.,
+                relevance: 1.
+            }, count).await?;
+            let recalled = recalled.into_iter().map(|el| el.content).collect::<Vec<_>>();
+
+            let chatgpt_info = context.plugin_data.get_data("ChatGPT")?;
+            invoke::<String>(chatgpt_info, "respond packed", recalled).await?
+        }
+        None => invoke::<String>(chatgpt_info, "respond", true).await?
+    };
+
+    let chatgpt_info = context.plugin_data.get_data("ChatGPT")?;
     invoke::<bool>(chatgpt_info, "push", ChatGPTMessage {
         role: ChatGPTRole::Assistant,
         content: content.clone()
@@ -157,13 +776,58 @@ pub async fn ask_chatgpt(context: &mut CommandContext, query: &str) -> Result<St
     Ok(content.clone())
 }
 
+/// Asks ChatGPT a one-off question with its own system prompt, without touching the conversation memory.
+pub async fn ask_chatgpt_ephemeral(context: &mut CommandContext, system: &str, query: &str) -> Result<String, Box<dyn Error>> {
+    let chatgpt_info = context.plugin_data.get_data("ChatGPT")?;
+
+    invoke::<String>(chatgpt_info, "respond ephemeral", EphemeralRequest {
+        messages: vec![
+            ChatGPTMessage {
+                role: ChatGPTRole::System,
+                content: system.to_string()
+            },
+            ChatGPTMessage {
+                role: ChatGPTRole::User,
+                content: query.to_string()
+            }
+        ],
+        settings: ResponseSettings::default()
+    }).await
+}
+
+/// Cuts a response down to its outermost JSON object, in case the model wrapped it in a code block.
+pub fn extract_json_object(response: &str) -> &str {
+    match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => response
+    }
+}
+
+/// Adds the tokens ChatGPT has used since the last call to the context's running total.
+pub async fn collect_chatgpt_usage(context: &mut CommandContext) -> Result<(), Box<dyn Error>> {
+    if let Ok(chatgpt_info) = context.plugin_data.get_data("ChatGPT") {
+        let tokens = invoke::<usize>(chatgpt_info, "take usage", true).await?;
+        context.token_budget.used += tokens;
+    }
+
+    Ok(())
+}
+
 pub async fn chatgpt(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
-    let prompt: String = args.get(0).ok_or(CommandNoArgError("ask-chatgpt", "prompt"))?.clone().try_into()?;
+    let args = CommandArgs::new("ask_chatgpt", &[ "prompt" ], &args);
+    let prompt = args.require_string("prompt")?;
     let response = ask_chatgpt(ctx, &prompt).await?;
     
     Ok(response.into())
 }
 
+pub async fn list_models(ctx: &mut CommandContext, _: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let models = invoke::<Vec<String>>(chatgpt_info, "list models", true).await?;
+
+    Ok(ScriptValue::List(models.into_iter().map(|el| el.into()).collect()))
+}
+
 pub async fn reset_chatgpt(ctx: &mut CommandContext, _: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
     let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
     invoke::<bool>(chatgpt_info, "clear", true).await?;
@@ -171,6 +835,18 @@ pub async fn reset_chatgpt(ctx: &mut CommandContext, _: Vec<ScriptValue>) -> Res
     Ok(ScriptValue::None)
 }
 
+pub async fn export_fine_tuning(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+
+    let len = invoke::<usize>(chatgpt_info, "len", true).await?;
+    if len == 0 {
+        return Ok("There is no conversation to export yet.".to_string().into());
+    }
+
+    let path = invoke::<String>(chatgpt_info, "export fine-tuning", true).await?;
+    Ok(format!("Exported {len} messages to {path}.").into())
+}
+
 pub struct ChatGPTImpl;
 
 #[async_trait]
@@ -184,6 +860,19 @@ impl CommandImpl for ChatGPTImpl {
     }
 }
 
+pub struct ExportFineTuningImpl;
+
+#[async_trait]
+impl CommandImpl for ExportFineTuningImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        export_fine_tuning(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
 pub struct ResetChatGPTImpl;
 
 #[async_trait]
@@ -197,6 +886,19 @@ impl CommandImpl for ResetChatGPTImpl {
     }
 }
 
+pub struct ListModelsImpl;
+
+#[async_trait]
+impl CommandImpl for ListModelsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        list_models(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
 pub struct ChatGPTCycle;
 
 #[async_trait]
@@ -205,13 +907,69 @@ impl PluginCycle for ChatGPTCycle {
         Ok(None)
     }
 
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>> {
-        let config: ChatGPTPluginConfig = serde_json::from_value(value).ok()?;
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let config: ChatGPTPluginConfig = serde_json::from_value(value)?;
+
+        let response_filters = config.response_filters.iter()
+            .map(|el| Ok((Regex::new(&el.pattern)?, el.replacement.clone())))
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+
+        // reqwest clients share their connection pool when cloned, so vision requests reuse the OpenAI client's.
+        let http = config.client.build_http_client()?;
+
+        let memory = match (&config.checkpoints.directory, config.checkpoints.load_latest) {
+            (Some(directory), true) => load_latest_checkpoint(directory)?
+                .map(|el| el.messages.into_iter().map(|el| el.into()).collect())
+                .unwrap_or_default(),
+            _ => vec![]
+        };
+
+        Ok(Some(Box::new(ChatGPTData {
+            client: config.client.build_openai_client(http.clone(), &config.api_key),
+            http,
+            api_key: config.api_key.clone(),
+            vision_model: config.vision_model.clone(),
+            client_settings: config.client.clone(),
+            max_title_length: config.max_title_length,
+            max_keywords: config.max_keywords.max(1),
+            throttle: Throttle::new(config.throttle.clone()),
+            idle_timeout: config.idle_timeout.map(Duration::from_secs),
+            last_activity: None,
+            embedding_model: config.embedding_model.clone(),
+            blocklist: compile_blocklist(&config.blocklist)?,
+            blocklist_refusal: config.blocklist_refusal.clone(),
+            system_prompt: config.render_system_prompt()?,
+            context_limits: default_context_limits().into_iter()
+                .chain(config.context_limits.clone())
+                .collect(),
+            user: config.user.clone(),
+            memory,
+            sessions: HashMap::new(),
+            response_filters,
+            unreported_tokens: 0,
+            models: config.models.clone(),
+            fine_tuning_path: config.fine_tuning_path.clone(),
+            dedupe_system_messages: config.dedupe_system_messages,
+            json_repair: config.json_repair,
+            packing: config.packing.clone(),
+            schemas: compile_named_schemas(&config.schemas)?,
+            checkpoints: Checkpoints::new(config.checkpoints.clone())
+        })))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<ChatGPTPluginConfig>(value.clone())?;
+
+        let config: ChatGPTPluginConfig = serde_json::from_value(value)?;
+        config.render_system_prompt()?;
+        config.client.build_http_client()?;
+        compile_blocklist(&config.blocklist)?;
+        compile_named_schemas(&config.schemas)?;
+        if config.models.len() == 0 {
+            return Err(Box::new(NoModelsError));
+        }
 
-        Some(Box::new(ChatGPTData {
-            client: Client::new().with_api_key(config.api_key.clone()),
-            memory: vec![]
-        }))
+        Ok(())
     }
 }
 
@@ -219,6 +977,7 @@ pub fn create_chatgpt() -> Plugin {
     Plugin {
         name: "ChatGPT".to_string(),
         dependencies: vec![],
+        default_timeout: Some(Duration::from_secs(180)),
         cycle: Box::new(ChatGPTCycle),
         commands: vec![
             Command {
@@ -236,6 +995,227 @@ pub fn create_chatgpt() -> Plugin {
                 args: vec![],
                 return_type: "None".to_string(),
                 run: Box::new(ResetChatGPTImpl)
+            },
+            Command {
+                name: "trim_memory".to_string(),
+                purpose: "Shorten the memory of ChatGPT to its system prompt and last few exchanges.".to_string(),
+                args: vec![
+                    CommandArgument::new("exchanges", "How many of the latest user and assistant exchanges to keep.", "Int")
+                ],
+                return_type: "Int".to_string(),
+                run: Box::new(TrimMemoryImpl)
+            },
+            Command {
+                name: "list_models".to_string(),
+                purpose: "List the OpenAI models available to ChatGPT.".to_string(),
+                args: vec![],
+                return_type: "String[]".to_string(),
+                run: Box::new(ListModelsImpl)
+            },
+            Command {
+                name: "translate".to_string(),
+                purpose: "Translate text into another language.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to translate.", "String"),
+                    CommandArgument::new("target_language", "The language to translate the text into.", "String")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(TranslateImpl)
+            },
+            Command {
+                name: "diff_chatgpt".to_string(),
+                purpose: "Compare ChatGPT's responses to one prompt under two different settings.".to_string(),
+                args: vec![
+                    CommandArgument::new("prompt", "The prompt to ask ChatGPT.", "String"),
+                    CommandArgument::new("first_settings", "The first settings to use.", "{ model?: String, temperature?: Float }"),
+                    CommandArgument::new("second_settings", "The second settings to use.", "{ model?: String, temperature?: Float }")
+                ],
+                return_type: "{ first: String, second: String, diff: String[] }".to_string(),
+                run: Box::new(DiffChatGPTImpl)
+            },
+            Command {
+                name: "compare_models".to_string(),
+                purpose: "Ask several models the same prompt at once, to compare their outputs and token usage.".to_string(),
+                args: vec![
+                    CommandArgument::new("prompt", "The prompt to ask every model.", "String"),
+                    CommandArgument::new("models", "The names of the models to ask.", "String[]")
+                ],
+                return_type: "{ [model: String]: { output?: String, error?: String, tokens: Int } }".to_string(),
+                run: Box::new(CompareModelsImpl)
+            },
+            Command {
+                name: "ask_chatgpt_json".to_string(),
+                purpose: "Ask ChatGPT for JSON matching a JSON schema, checking its response against the schema.".to_string(),
+                args: vec![
+                    CommandArgument::new("prompt", "What to ask ChatGPT.", "String"),
+                    CommandArgument::new("schema", "The JSON schema, or the name of one from the config.", "Any")
+                ],
+                return_type: "Any | { error: String, problems: String[], output: String }".to_string(),
+                run: Box::new(AskChatGPTJsonImpl)
+            },
+            Command {
+                name: "check_context_fit".to_string(),
+                purpose: "Check whether messages plus a completion fit in a model's context window.".to_string(),
+                args: vec![
+                    CommandArgument::new("model", "The model to check against.", "String"),
+                    CommandArgument::new("completion_tokens", "How many tokens to leave for the completion.", "Int"),
+                    CommandArgument::optional("messages", "Optional. The messages to check. Uses ChatGPT's memory if left out.", "{ role: \"System\" | \"User\" | \"Assistant\", content: String }[]")
+                ],
+                return_type: "{ fits: bool, prompt_tokens: Int, completion_tokens: Int, context_limit: Int, headroom: Int }".to_string(),
+                run: Box::new(CheckContextFitImpl)
+            },
+            Command {
+                name: "pack_context".to_string(),
+                purpose: "Fit the system prompt, recent messages, and recalled memories into the context window, dropping what doesn't fit.".to_string(),
+                args: vec![
+                    CommandArgument::new("recalled", "The recalled memories, most relevant first.", "String[]"),
+                    CommandArgument::optional("messages", "Optional. The conversation to pack. Uses ChatGPT's memory if left out.", "{ role: \"System\" | \"User\" | \"Assistant\", content: String }[]"),
+                    CommandArgument::optional("model", "Optional. The model whose context limit to use.", "String")
+                ],
+                return_type: "{ messages: { role: \"System\" | \"User\" | \"Assistant\", content: String }[], tokens: Int, budget: Int, dropped_messages: Int, dropped_recall: Int }".to_string(),
+                run: Box::new(PackContextImpl)
+            },
+            Command {
+                name: "handoff_note".to_string(),
+                purpose: "Summarize the conversation with ChatGPT into a handoff note of goals, decisions, and open questions. Does not change ChatGPT's memory.".to_string(),
+                args: vec![],
+                return_type: "String".to_string(),
+                run: Box::new(HandoffNoteImpl)
+            },
+            Command {
+                name: "title".to_string(),
+                purpose: "Write a short title for the conversation with ChatGPT. Does not change ChatGPT's memory.".to_string(),
+                args: vec![],
+                return_type: "String | None".to_string(),
+                run: Box::new(TitleImpl)
+            },
+            Command {
+                name: "follow_ups".to_string(),
+                purpose: "Suggest follow-up questions to the conversation with ChatGPT. Does not change ChatGPT's memory.".to_string(),
+                args: vec![
+                    CommandArgument::optional("count", "Optional. How many questions to suggest, up to 10. Defaults to 3.", "Int")
+                ],
+                return_type: "String[]".to_string(),
+                run: Box::new(FollowUpsImpl)
+            },
+            Command {
+                name: "elaborate".to_string(),
+                purpose: "Expand a short answer with more detail and examples. Uses ChatGPT's last response if no text is given.".to_string(),
+                args: vec![
+                    CommandArgument::optional("text", "Optional. The answer to expand.", "String"),
+                    CommandArgument::optional("save", "Optional. Whether to replace ChatGPT's last response with the expanded one. Only when no text is given. Defaults to false.", "bool")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(ElaborateImpl)
+            },
+            Command {
+                name: "save_session".to_string(),
+                purpose: "Save a copy of the conversation with ChatGPT as a named session.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name to save the session as.", "String")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(SaveSessionImpl)
+            },
+            Command {
+                name: "merge_sessions".to_string(),
+                purpose: "Append one ChatGPT session's messages onto another's. A session left out means the current conversation.".to_string(),
+                args: vec![
+                    CommandArgument::optional("from", "Optional. The session to take messages from.", "String"),
+                    CommandArgument::optional("into", "Optional. The session to add them to.", "String"),
+                    CommandArgument::optional("summarize", "Optional. Whether to add a summary of the messages instead of the messages themselves. Defaults to false.", "bool")
+                ],
+                return_type: "{ messages: Int }".to_string(),
+                run: Box::new(MergeSessionsImpl)
+            },
+            Command {
+                name: "list_checkpoints".to_string(),
+                purpose: "List the saved checkpoints of the conversation with ChatGPT, oldest first.".to_string(),
+                args: vec![],
+                return_type: "{ name: String, created: String, messages: Int }[]".to_string(),
+                run: Box::new(ListCheckpointsImpl)
+            },
+            Command {
+                name: "load_checkpoint".to_string(),
+                purpose: "Replace the conversation with ChatGPT with a saved checkpoint.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name of the checkpoint, from list_checkpoints.", "String")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(LoadCheckpointImpl)
+            },
+            Command {
+                name: "delete_checkpoint".to_string(),
+                purpose: "Delete a saved checkpoint of the conversation with ChatGPT.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name of the checkpoint, from list_checkpoints.", "String")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(DeleteCheckpointImpl)
+            },
+            Command {
+                name: "classify".to_string(),
+                purpose: "Classify text into one of the given categories.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to classify.", "String"),
+                    CommandArgument::new("categories", "The categories to choose from.", "String[]")
+                ],
+                return_type: "{ category: String | None, confidence: Float, explanation: String }".to_string(),
+                run: Box::new(ClassifyImpl)
+            },
+            Command {
+                name: "calc".to_string(),
+                purpose: "Work out a math problem. The answer is checked by evaluating the expression, not trusted from ChatGPT.".to_string(),
+                args: vec![
+                    CommandArgument::new("problem", "The problem to solve, in words or as an expression.", "String")
+                ],
+                return_type: "{ expression: String, answer: Float, verified: bool, model_answer?: Float | None, note?: String }".to_string(),
+                run: Box::new(CalcImpl)
+            },
+            Command {
+                name: "embed".to_string(),
+                purpose: "Get the embedding vector of a text, or of each text in a list.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text or texts to embed.", "String | String[]"),
+                    CommandArgument::optional("model", "Optional. The embedding model to use, instead of the configured one.", "String")
+                ],
+                return_type: "Float[] | Float[][]".to_string(),
+                run: Box::new(EmbedImpl)
+            },
+            Command {
+                name: "extract_entities".to_string(),
+                purpose: "Extract named entities, like people, organizations, places, and dates, from text.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to extract entities from.", "String")
+                ],
+                return_type: "{ text: String, type: String, start: Int | None, end: Int | None }[]".to_string(),
+                run: Box::new(ExtractEntitiesImpl)
+            },
+            Command {
+                name: "keywords".to_string(),
+                purpose: "Extract the most salient keywords and key phrases from text, most important first.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to extract keywords from.", "String"),
+                    CommandArgument::optional("max", "Optional. The most keywords to return. Defaults to the config.", "Int")
+                ],
+                return_type: "String[]".to_string(),
+                run: Box::new(KeywordsImpl)
+            },
+            Command {
+                name: "export_fine_tuning".to_string(),
+                purpose: "Save the conversation with ChatGPT as one example in an OpenAI fine-tuning JSONL file.".to_string(),
+                args: vec![],
+                return_type: "String".to_string(),
+                run: Box::new(ExportFineTuningImpl)
+            },
+            Command {
+                name: "plan_commands".to_string(),
+                purpose: "Write out the commands you would run to reach a goal, without running them.".to_string(),
+                args: vec![
+                    CommandArgument::new("goal", "The goal to plan for.", "String")
+                ],
+                return_type: "{ steps: { command: String, args: Any[], known: bool }[], \"unknown commands\": String[] }".to_string(),
+                run: Box::new(PlanCommandsImpl)
             }
         ]
     }
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, error::Error};
+
+use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::async_openai::num_tokens_from_messages;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ChatGPTData, ChatGPTMessage, ChatGPTRole, UnknownContextLimitError, NoModelsError};
+
+/// The parts of a packed context, which are given room in the configured order.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ContextPart {
+    #[serde(rename = "system prompt")] SystemPrompt,
+    #[serde(rename = "recent")] Recent,
+    #[serde(rename = "recall")] Recall
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PackingConfig {
+    /// The token budget. Defaults to the model's context limit.
+    #[serde(rename = "max context tokens", default)] pub max_context_tokens: Option<usize>,
+    /// Tokens kept free for the response.
+    #[serde(rename = "reserved tokens", default = "default_reserved_tokens")] pub reserved_tokens: usize,
+    #[serde(default = "default_priorities")] pub priorities: Vec<ContextPart>,
+    /// The most of the budget that recent messages may take.
+    #[serde(rename = "recent share", default = "default_recent_share")] pub recent_share: f64,
+    /// The most of the budget that recalled memories may take.
+    #[serde(rename = "recall share", default = "default_recall_share")] pub recall_share: f64,
+    /// How many memories `ask_chatgpt` recalls for each prompt and packs in with the conversation.
+    /// Without it, the conversation is sent as it is.
+    #[serde(rename = "recall count", default)] pub recall_count: Option<usize>
+}
+
+impl Default for PackingConfig {
+    fn default() -> Self {
+        Self {
+            max_context_tokens: None,
+            reserved_tokens: default_reserved_tokens(),
+            priorities: default_priorities(),
+            recent_share: default_recent_share(),
+            recall_share: default_recall_share(),
+            recall_count: None
+        }
+    }
+}
+
+fn default_reserved_tokens() -> usize {
+    500
+}
+
+fn default_priorities() -> Vec<ContextPart> {
+    vec![ ContextPart::SystemPrompt, ContextPart::Recent, ContextPart::Recall ]
+}
+
+fn default_recent_share() -> f64 {
+    1.
+}
+
+fn default_recall_share() -> f64 {
+    0.3
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PackRequest {
+    /// Recalled memories, most relevant first.
+    pub recalled: Vec<String>,
+    /// The conversation to pack. Defaults to the ChatGPT plugin's own.
+    #[serde(default)] pub messages: Option<Vec<ChatGPTMessage>>,
+    #[serde(default)] pub model: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PackedContext {
+    pub messages: Vec<ChatGPTMessage>,
+    pub tokens: usize,
+    pub budget: usize,
+    pub dropped_messages: usize,
+    pub dropped_recall: usize
+}
+
+fn count_tokens(model: &str, messages: &[ChatGPTMessage]) -> Result<usize, Box<dyn Error>> {
+    let messages: Vec<ChatCompletionRequestMessage> = messages.iter()
+        .map(|el| el.clone().into())
+        .collect();
+    Ok(num_tokens_from_messages(model, &messages)?)
+}
+
+fn recall_message(entries: &[&String]) -> ChatGPTMessage {
+    ChatGPTMessage {
+        role: ChatGPTRole::System,
+        content: format!(
+            "Relevant memories:\n{}",
+            entries.iter().map(|el| format!("- {el}")).collect::<Vec<_>>().join("\n")
+        )
+    }
+}
+
+impl ChatGPTData {
+    /// Fits the system prompt, recent messages, and recalled memories into the token budget. Each part
+    /// takes what it can in priority order, up to its share of the budget. Recent messages are kept
+    /// newest first and without gaps, and memories most relevant first.
+    pub fn pack_context(&self, request: PackRequest) -> Result<PackedContext, Box<dyn Error>> {
+        let model = match request.model {
+            Some(model) => model,
+            None => self.models.first().cloned().ok_or(NoModelsError)?
+        };
+        let limit = match self.packing.max_context_tokens {
+            Some(limit) => limit,
+            None => self.context_limits.get(&model).cloned().ok_or(UnknownContextLimitError(model.clone()))?
+        };
+        let budget = limit.saturating_sub(self.packing.reserved_tokens);
+
+        let system = ChatGPTMessage {
+            role: ChatGPTRole::System,
+            content: self.system_prompt.clone()
+        };
+        let recent = request.messages
+            .unwrap_or_else(|| self.memory.iter().map(|el| el.clone().into()).collect())
+            .into_iter()
+            .filter(|el| !(matches!(el.role, ChatGPTRole::System) && el.content == self.system_prompt))
+            .collect::<Vec<_>>();
+
+        let mut used = 0;
+        let mut include_system = false;
+        let mut kept_recent: Vec<ChatGPTMessage> = vec![];
+        let mut kept_recall: Vec<&String> = vec![];
+        for part in &self.packing.priorities {
+            let room = budget.saturating_sub(used);
+            match part {
+                ContextPart::SystemPrompt => {
+                    let tokens = count_tokens(&model, &[ system.clone() ])?;
+                    if tokens <= room {
+                        include_system = true;
+                        used += tokens;
+                    }
+                }
+                ContextPart::Recent => {
+                    let room = room.min((budget as f64 * self.packing.recent_share) as usize);
+                    let mut part_used = 0;
+                    for message in recent.iter().rev() {
+                        let tokens = count_tokens(&model, &[ message.clone() ])?;
+                        if part_used + tokens > room {
+                            break;
+                        }
+                        kept_recent.insert(0, message.clone());
+                        part_used += tokens;
+                    }
+                    used += part_used;
+                }
+                ContextPart::Recall => {
+                    let room = room.min((budget as f64 * self.packing.recall_share) as usize);
+                    let mut part_used = 0;
+                    // A memory too long to fit is skipped, so that shorter, less relevant ones still can.
+                    for entry in &request.recalled {
+                        let mut entries = kept_recall.clone();
+                        entries.push(entry);
+                        let tokens = count_tokens(&model, &[ recall_message(&entries) ])?;
+                        if tokens <= room {
+                            kept_recall = entries;
+                            part_used = tokens;
+                        }
+                    }
+                    used += part_used;
+                }
+            }
+        }
+
+        let mut messages: Vec<ChatGPTMessage> = vec![];
+        if include_system {
+            messages.push(system);
+        }
+        if kept_recall.len() > 0 {
+            messages.push(recall_message(&kept_recall));
+        }
+        let dropped_messages = recent.len() - kept_recent.len();
+        let dropped_recall = request.recalled.len() - kept_recall.len();
+        messages.extend(kept_recent);
+
+        Ok(PackedContext {
+            tokens: count_tokens(&model, &messages)?,
+            messages,
+            budget,
+            dropped_messages,
+            dropped_recall
+        })
+    }
+}
+
+pub async fn pack_context(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("pack_context", &[ "recalled", "messages", "model" ], &args);
+    let recalled: Vec<String> = args.require_as("recalled", "a list of strings")?;
+    let messages: Option<Vec<ChatGPTMessage>> = args.optional_as("messages", "a list of messages")?;
+    let model = args.optional_string("model")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let packed = invoke::<PackedContext>(chatgpt_info, "pack context", PackRequest { recalled, messages, model }).await?;
+
+    Ok(serde_json::from_value(serde_json::to_value(packed)?)?)
+}
+
+pub struct PackContextImpl;
+
+#[async_trait]
+impl CommandImpl for PackContextImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        pack_context(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
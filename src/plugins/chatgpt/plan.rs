@@ -0,0 +1,79 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, describe_command};
+
+use super::{ask_chatgpt_ephemeral, parse_model_json};
+
+const PLAN_PROMPT: &str = r#"You plan which commands to run to reach a goal. You will be given the available commands and a goal. Do not run anything; only write the plan.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "steps": [
+        { "command": "...", "args": [ ... ] }
+    ]
+}
+
+Only use the commands you are given."#;
+
+#[derive(Serialize, Deserialize)]
+pub struct PlanStep {
+    pub command: String,
+    #[serde(default)] pub args: Vec<ScriptValue>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>
+}
+
+pub async fn plan_commands(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("plan_commands", &[ "goal" ], &args);
+    let goal = args.require_string("goal")?;
+
+    let specs = ctx.commands.iter()
+        .map(describe_command)
+        .collect::<Vec<_>>()
+        .join("");
+    let query = format!("Commands:\n{}\n\nGoal: {goal}", specs.trim_end());
+
+    let response = ask_chatgpt_ephemeral(ctx, PLAN_PROMPT, &query).await?;
+    let plan: Plan = parse_model_json(ctx, &response).await?;
+
+    let mut unknown: Vec<ScriptValue> = vec![];
+    let steps = plan.steps.into_iter()
+        .map(|step| {
+            let known = ctx.commands.iter().any(|el| el.name == step.command);
+            if !known {
+                unknown.push(step.command.clone().into());
+            }
+
+            ScriptValue::Dict(HashMap::from_iter([
+                ("command".to_string(), step.command.into()),
+                ("args".to_string(), step.args.into()),
+                ("known".to_string(), known.into())
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("steps".to_string(), steps.into()),
+        ("unknown commands".to_string(), unknown.into())
+    ])))
+}
+
+pub struct PlanCommandsImpl;
+
+#[async_trait]
+impl CommandImpl for PlanCommandsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        plan_commands(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+
+use colored::Colorize;
+use serde::de::DeserializeOwned;
+
+use crate::{CommandContext, invoke};
+
+use super::{ask_chatgpt_ephemeral, extract_json_object};
+
+const FIX_JSON_PROMPT: &str = r#"You fix malformed JSON. The user sends you JSON that could not be parsed, along with the parser's error.
+
+Respond with only the corrected JSON, keeping its content the same, and nothing else."#;
+
+/// Cuts text down to its outermost JSON object or array, dropping code fences and any prose around it.
+fn trim_to_json(text: &str) -> &str {
+    let start = text.find(|c: char| c == '{' || c == '[');
+    let end = text.rfind(|c: char| c == '}' || c == ']');
+
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text.trim()
+    }
+}
+
+/// Fixes the common ways a model's JSON is malformed: code fences and prose around it, trailing commas,
+/// and raw line breaks inside strings.
+pub fn repair_json(response: &str) -> String {
+    let json = trim_to_json(response);
+    let chars = json.chars().collect::<Vec<_>>();
+
+    let mut repaired = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (ind, &c) in chars.iter().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                '\n' => {
+                    repaired.push_str("\\n");
+                    continue;
+                }
+                '\r' => continue,
+                '\t' => {
+                    repaired.push_str("\\t");
+                    continue;
+                }
+                _ => {}
+            }
+            repaired.push(c);
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            // A comma with nothing but whitespace before the closing bracket is a trailing comma.
+            ',' => {
+                let next = chars[ind + 1..].iter().find(|el| !el.is_whitespace());
+                if let Some('}' | ']') = next {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        repaired.push(c);
+    }
+
+    repaired
+}
+
+/// Parses JSON that the model was asked for. If it's malformed, it is repaired, and if that fails too,
+/// the model is asked once to fix it. With 'json repair' off, nothing is repaired locally, but the model
+/// is still asked once.
+pub async fn parse_model_json<T : DeserializeOwned>(ctx: &mut CommandContext, response: &str) -> Result<T, Box<dyn Error>> {
+    let err = match serde_json::from_str(extract_json_object(response)) {
+        Ok(parsed) => return Ok(parsed),
+        Err(err) => err
+    };
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let repair = invoke::<bool>(chatgpt_info, "get json repair", true).await?;
+
+    let (response, err) = match repair {
+        true => {
+            let repaired = repair_json(response);
+            match serde_json::from_str(&repaired) {
+                Ok(parsed) => return Ok(parsed),
+                Err(err) => (repaired, err)
+            }
+        }
+        false => (response.to_string(), err)
+    };
+
+    println!("{}: Could not parse ChatGPT's JSON, asking it to fix it: {}", "Warning".yellow(), err);
+    let fixed = ask_chatgpt_ephemeral(ctx, FIX_JSON_PROMPT, &format!("Error: {err}\n\nJSON:\n{response}")).await?;
+
+    Ok(match repair {
+        true => serde_json::from_str(&repair_json(&fixed))?,
+        false => serde_json::from_str(extract_json_object(&fixed))?
+    })
+}
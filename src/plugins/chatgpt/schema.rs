@@ -0,0 +1,184 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ChatGPTMessage, ChatGPTRole, EphemeralRequest, ResponseSettings, repair_json, extract_json_object};
+
+#[derive(Debug, Clone)]
+pub struct InvalidSchemaError(pub String);
+
+impl Display for InvalidSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the JSON schema is not valid: {}", self.0)
+    }
+}
+
+impl Error for InvalidSchemaError {}
+
+#[derive(Debug, Clone)]
+pub struct NoSchemaError(pub String);
+
+impl Display for NoSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "there is no schema named '{}' in the ChatGPT plugin's 'schemas'.", self.0)
+    }
+}
+
+impl Error for NoSchemaError {}
+
+pub fn compile_schema(schema: &Value) -> Result<JSONSchema, InvalidSchemaError> {
+    JSONSchema::compile(schema).map_err(|err| InvalidSchemaError(err.to_string()))
+}
+
+/// A schema from the config, compiled once when the plugin is loaded.
+pub struct NamedSchema {
+    pub schema: Value,
+    pub compiled: JSONSchema
+}
+
+pub fn compile_named_schemas(schemas: &HashMap<String, Value>) -> Result<HashMap<String, NamedSchema>, InvalidSchemaError> {
+    schemas.iter()
+        .map(|(name, schema)| Ok((name.clone(), NamedSchema {
+            schema: schema.clone(),
+            compiled: compile_schema(schema)?
+        })))
+        .collect()
+}
+
+/// Where `ask_chatgpt_json`'s schema lives: compiled in the plugin's data, or compiled for this one call.
+enum SchemaSource {
+    Named(String),
+    Inline(JSONSchema)
+}
+
+/// Asks for a response to be checked against a schema from the config.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SchemaCheckRequest {
+    pub name: String,
+    pub response: String
+}
+
+/// Everything about `instance` that doesn't match the schema, each with where it is.
+pub fn validate_against_schema(compiled: &JSONSchema, instance: &Value) -> Vec<String> {
+    match compiled.validate(instance) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .map(|el| match el.instance_path.to_string().as_str() {
+                "" => el.to_string(),
+                path => format!("{path}: {el}")
+            })
+            .collect()
+    }
+}
+
+/// Parses a response as JSON, repairing it first if `repair` is on, and checks it against the schema.
+/// Returns the value, or what's wrong with it.
+pub fn check_response(compiled: &JSONSchema, response: &str, repair: bool) -> Result<Value, Vec<String>> {
+    let response = match repair {
+        true => repair_json(response),
+        false => extract_json_object(response).to_string()
+    };
+    let value: Value = match serde_json::from_str(&response) {
+        Ok(value) => value,
+        Err(err) => return Err(vec![ format!("the response is not valid JSON: {err}") ])
+    };
+
+    let problems = validate_against_schema(compiled, &value);
+    match problems.len() {
+        0 => Ok(value),
+        _ => Err(problems)
+    }
+}
+
+pub async fn ask_chatgpt_json(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("ask_chatgpt_json", &[ "prompt", "schema" ], &args);
+    let prompt = args.require_string("prompt")?;
+
+    // A schema is either named from the config, where it's already compiled, or given inline.
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let repair = invoke::<bool>(chatgpt_info, "get json repair", true).await?;
+    let (schema, source) = match args.require("schema")? {
+        ScriptValue::String(name) => {
+            let schema = invoke::<Option<Value>>(chatgpt_info, "get schema", name).await?
+                .ok_or_else(|| NoSchemaError(name.clone()))?;
+            (schema, SchemaSource::Named(name.clone()))
+        }
+        _ => {
+            let schema: Value = args.require_as("schema", "a JSON schema, or the name of one")?;
+            let compiled = compile_schema(&schema)?;
+            (schema, SchemaSource::Inline(compiled))
+        }
+    };
+
+    let mut messages = vec![
+        ChatGPTMessage {
+            role: ChatGPTRole::System,
+            content: format!(
+                "Respond with only JSON, and nothing else. Your response must match this JSON schema:\n\n{}",
+                serde_json::to_string_pretty(&schema)?
+            )
+        },
+        ChatGPTMessage { role: ChatGPTRole::User, content: prompt }
+    ];
+
+    // The model gets one more try, told what was wrong with its first response.
+    let mut problems = vec![];
+    let mut response = String::new();
+    for attempt in 0..2 {
+        if attempt > 0 {
+            messages.push(ChatGPTMessage { role: ChatGPTRole::Assistant, content: response.clone() });
+            messages.push(ChatGPTMessage {
+                role: ChatGPTRole::User,
+                content: format!(
+                    "Your response did not match the schema:\n{}\n\nRespond again with corrected JSON only.",
+                    problems.iter().map(|el| format!("- {el}")).collect::<Vec<_>>().join("\n")
+                )
+            });
+        }
+
+        let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+        response = invoke::<String>(chatgpt_info, "respond ephemeral", EphemeralRequest {
+            messages: messages.clone(),
+            settings: ResponseSettings::default()
+        }).await?;
+
+        let checked = match &source {
+            SchemaSource::Inline(compiled) => check_response(compiled, &response, repair),
+            SchemaSource::Named(name) => {
+                let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+                invoke::<Result<Value, Vec<String>>>(chatgpt_info, "check schema response", SchemaCheckRequest {
+                    name: name.clone(),
+                    response: response.clone()
+                }).await?
+            }
+        };
+        match checked {
+            Ok(value) => return Ok(serde_json::from_value(value)?),
+            Err(found) => problems = found
+        }
+    }
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("error".to_string(), "the response did not match the schema.".to_string().into()),
+        ("problems".to_string(), ScriptValue::List(problems.into_iter().map(|el| el.into()).collect())),
+        ("output".to_string(), response.into())
+    ])))
+}
+
+pub struct AskChatGPTJsonImpl;
+
+#[async_trait]
+impl CommandImpl for AskChatGPTJsonImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        ask_chatgpt_json(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,167 @@
+use std::{error::Error, fmt::Display, collections::HashMap};
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::{ChatGPTData, ResponseSettings, transcript};
+
+const SESSION_SUMMARY_PROMPT: &str = r#"You summarize conversations. You will be given a conversation between a user and an assistant. Summarize what was asked, what was found, and what was decided, so that it can be continued without the full conversation.
+
+Respond with only the summary."#;
+
+#[derive(Debug, Clone)]
+pub struct NoSessionError(pub String);
+
+impl Display for NoSessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "there is no saved ChatGPT session named '{}'.", self.0)
+    }
+}
+
+impl Error for NoSessionError {}
+
+#[derive(Debug, Clone)]
+pub struct MergeIntoSelfError(pub String);
+
+impl Display for MergeIntoSelfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot merge {} into itself.", self.0)
+    }
+}
+
+impl Error for MergeIntoSelfError {}
+
+/// Appends the `from` session into the `into` session. A session left out means ChatGPT's current memory.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MergeSessionsRequest {
+    pub from: Option<String>,
+    pub into: Option<String>,
+    #[serde(default)] pub summarize: bool
+}
+
+/// Appends messages while keeping the conversation valid: system messages already in `target` are skipped,
+/// and a message with the same role as the one before it is joined onto it, so user and assistant turns alternate.
+pub fn append_messages(target: &mut Vec<ChatCompletionRequestMessage>, messages: Vec<ChatCompletionRequestMessage>) {
+    for message in messages {
+        if message.role == Role::System && target.iter().any(|el| el.role == Role::System && el.content == message.content) {
+            continue;
+        }
+
+        match target.last_mut() {
+            Some(last) if last.role == message.role && message.role != Role::System => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&message.content);
+            }
+            _ => target.push(message)
+        }
+    }
+}
+
+impl ChatGPTData {
+    fn get_session(&self, name: &Option<String>) -> Result<Vec<ChatCompletionRequestMessage>, Box<dyn Error>> {
+        match name {
+            Some(name) => Ok(self.sessions.get(name).cloned().ok_or(NoSessionError(name.clone()))?),
+            None => Ok(self.memory.clone())
+        }
+    }
+
+    /// Merges one session into another, returning how many messages the merged session has.
+    pub async fn merge_sessions(&mut self, request: MergeSessionsRequest) -> Result<usize, Box<dyn Error>> {
+        // Merging a session into itself would only duplicate its messages.
+        if request.from == request.into {
+            let name = match &request.from {
+                Some(name) => format!("the session '{name}'"),
+                None => "the current conversation".to_string()
+            };
+            return Err(Box::new(MergeIntoSelfError(name)));
+        }
+
+        let mut messages = self.get_session(&request.from)?;
+        let mut target = self.get_session(&request.into)?;
+
+        if request.summarize {
+            let transcript = transcript(&messages);
+
+            messages = if transcript.len() > 0 {
+                let summary = self.respond(vec![
+                    ChatCompletionRequestMessage { role: Role::System, content: SESSION_SUMMARY_PROMPT.to_string(), name: None },
+                    ChatCompletionRequestMessage { role: Role::User, content: transcript, name: None }
+                ], ResponseSettings::default()).await?;
+
+                let from = request.from.as_deref().unwrap_or("the current conversation");
+                vec![ ChatCompletionRequestMessage {
+                    role: Role::System,
+                    content: format!("Summary of a merged session ({from}):\n{}", summary.trim()),
+                    name: None
+                } ]
+            } else {
+                vec![]
+            };
+        }
+
+        append_messages(&mut target, messages);
+        let length = target.len();
+
+        match request.into {
+            Some(name) => { self.sessions.insert(name, target); }
+            None => self.memory = target
+        }
+
+        Ok(length)
+    }
+}
+
+pub async fn save_session(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("save_session", &[ "name" ], &args);
+    let name = args.require_string("name")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let length = invoke::<usize>(chatgpt_info, "save session", &name).await?;
+
+    Ok(format!("Saved {length} messages as the session '{name}'.").into())
+}
+
+pub async fn merge_sessions(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("merge_sessions", &[ "from", "into", "summarize" ], &args);
+    let request = MergeSessionsRequest {
+        from: args.optional_string("from")?,
+        into: args.optional_string("into")?,
+        summarize: args.optional_bool("summarize")?.unwrap_or(false)
+    };
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let length = invoke::<usize>(chatgpt_info, "merge sessions", request).await?;
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("messages".to_string(), ScriptValue::Int(length as i64))
+    ])))
+}
+
+pub struct SaveSessionImpl;
+
+#[async_trait]
+impl CommandImpl for SaveSessionImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        save_session(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct MergeSessionsImpl;
+
+#[async_trait]
+impl CommandImpl for MergeSessionsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        merge_sessions(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,151 @@
+use std::{sync::Mutex, time::{Duration, Instant}};
+
+use async_openai::error::OpenAIError;
+use colored::Colorize;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThrottleConfig {
+    #[serde(default)] pub enabled: bool,
+    #[serde(rename = "min remaining requests", default = "default_min_remaining_requests")] pub min_remaining_requests: usize,
+    #[serde(rename = "min remaining tokens", default = "default_min_remaining_tokens")] pub min_remaining_tokens: usize
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_remaining_requests: default_min_remaining_requests(),
+            min_remaining_tokens: default_min_remaining_tokens()
+        }
+    }
+}
+
+fn default_min_remaining_requests() -> usize {
+    1
+}
+
+fn default_min_remaining_tokens() -> usize {
+    1000
+}
+
+/// What OpenAI last said about the rate limits, and when they reset.
+#[derive(Default)]
+pub struct RateLimitState {
+    pub remaining_requests: Option<usize>,
+    pub remaining_tokens: Option<usize>,
+    pub requests_reset_at: Option<Instant>,
+    pub tokens_reset_at: Option<Instant>
+}
+
+static DURATION_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+(?:\.\d+)?)(ms|h|m|s)").unwrap());
+static TRY_AGAIN_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)try again in ([\d.]+(?:ms|s|m|h)(?:[\d.]+(?:ms|s))?)").unwrap());
+
+/// Parses OpenAI's reset durations, like "20ms", "1.5s", or "6m0s".
+pub fn parse_reset_duration(text: &str) -> Option<Duration> {
+    let mut seconds = 0.;
+    let mut matched = false;
+    for capture in DURATION_PATTERN.captures_iter(text) {
+        let value: f64 = capture[1].parse().ok()?;
+        seconds += match &capture[2] {
+            "ms" => value / 1000.,
+            "s" => value,
+            "m" => value * 60.,
+            _ => value * 3600.
+        };
+        matched = true;
+    }
+
+    matched.then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Throttles requests before they hit OpenAI's rate limits, instead of waiting for a 429.
+pub struct Throttle {
+    pub config: ThrottleConfig,
+    pub state: Mutex<RateLimitState>
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self { config, state: Mutex::new(RateLimitState::default()) }
+    }
+
+    /// Records the `x-ratelimit-*` headers of a response.
+    pub fn record_headers(&self, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|el| el.to_str().ok());
+
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if let Some(remaining) = header("x-ratelimit-remaining-requests").and_then(|el| el.parse().ok()) {
+            state.remaining_requests = Some(remaining);
+        }
+        if let Some(remaining) = header("x-ratelimit-remaining-tokens").and_then(|el| el.parse().ok()) {
+            state.remaining_tokens = Some(remaining);
+        }
+        if let Some(reset) = header("x-ratelimit-reset-requests").and_then(parse_reset_duration) {
+            state.requests_reset_at = Some(now + reset);
+        }
+        if let Some(reset) = header("x-ratelimit-reset-tokens").and_then(parse_reset_duration) {
+            state.tokens_reset_at = Some(now + reset);
+        }
+    }
+
+    /// Rate limit errors are also read for their "try again in", in case a proxy in between drops the headers.
+    pub fn record_error(&self, err: &OpenAIError) {
+        let message = match err {
+            OpenAIError::ApiError(err) => err.message.clone(),
+            _ => return
+        };
+        if !message.to_lowercase().contains("rate limit") {
+            return;
+        }
+
+        let reset = TRY_AGAIN_PATTERN.captures(&message)
+            .and_then(|el| parse_reset_duration(&el[1]))
+            .unwrap_or(Duration::from_secs(1));
+
+        let mut state = self.state.lock().unwrap();
+        state.remaining_requests = Some(0);
+        state.requests_reset_at = Some(Instant::now() + reset);
+    }
+
+    /// How long to wait before the next request, if the limits are nearly used up.
+    pub fn delay(&self) -> Option<Duration> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        let mut delay = None;
+        let limits = [
+            (state.remaining_requests, self.config.min_remaining_requests, state.requests_reset_at),
+            (state.remaining_tokens, self.config.min_remaining_tokens, state.tokens_reset_at)
+        ];
+        for (remaining, min_remaining, reset_at) in limits {
+            if let (Some(remaining), Some(reset_at)) = (remaining, reset_at) {
+                if remaining <= min_remaining && reset_at > now {
+                    delay = Some(delay.unwrap_or(Duration::ZERO).max(reset_at - now));
+                }
+            }
+        }
+
+        delay
+    }
+
+    pub async fn wait(&self) {
+        if let Some(delay) = self.delay() {
+            println!("{}: OpenAI's rate limit is nearly used up, waiting {:?}.", "Warning".yellow(), delay);
+            sleep(delay).await;
+
+            // The limits have reset, so the old numbers no longer apply.
+            let mut state = self.state.lock().unwrap();
+            *state = RateLimitState::default();
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::error::Error;
+
+use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, ScriptValue, invoke};
+
+use super::{ChatGPTData, ChatGPTMessage, ChatGPTRole, ResponseSettings};
+
+const TITLE_PROMPT: &str = r#"You title conversations. You will be given a conversation between a user and an assistant. Write a short title for it, of a few words, like the subject line of an email.
+
+Respond with only the title."#;
+
+/// Keeps the first line of a title, without surrounding quotes, cut off after `max_length` characters.
+pub fn clean_title(title: &str, max_length: usize) -> String {
+    let title = title.lines()
+        .map(|el| el.trim())
+        .find(|el| !el.is_empty())
+        .unwrap_or("");
+    let title = title.trim_start_matches(|c: char| c == '"' || c == '\'' || c == '`' || c == '“')
+        .trim_end_matches(|c: char| c == '"' || c == '\'' || c == '`' || c == '”' || c == '.')
+        .replace('"', "");
+
+    match title.char_indices().nth(max_length) {
+        Some((end, _)) => {
+            // Cut at a word boundary when there is one, so titles don't end halfway through a word.
+            let cut = &title[..end];
+            let cut = cut.rfind(' ').map(|el| &cut[..el]).unwrap_or(cut);
+            format!("{}...", cut.trim_end())
+        }
+        None => title.trim().to_string()
+    }
+}
+
+impl ChatGPTData {
+    /// Titles the conversation in memory, without changing it. Returns `None` if there's no conversation yet.
+    pub async fn generate_title(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        let transcript = self.transcript();
+
+        if transcript.len() == 0 {
+            return Ok(None);
+        }
+
+        let messages: Vec<ChatCompletionRequestMessage> = vec![
+            ChatGPTMessage { role: ChatGPTRole::System, content: TITLE_PROMPT.to_string() }.into(),
+            ChatGPTMessage { role: ChatGPTRole::User, content: transcript }.into()
+        ];
+        let title = self.respond(messages, ResponseSettings::default()).await?;
+
+        Ok(Some(clean_title(&title, self.max_title_length)))
+    }
+}
+
+pub async fn title(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let title = invoke::<Option<String>>(chatgpt_info, "title", true).await?;
+
+    Ok(match title {
+        Some(title) => title.into(),
+        None => ScriptValue::None
+    })
+}
+
+pub struct TitleImpl;
+
+#[async_trait]
+impl CommandImpl for TitleImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        title(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,35 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue};
+
+use super::ask_chatgpt_ephemeral;
+
+const TRANSLATE_PROMPT: &str = r#"You are a translator. Translate the text that the user sends you into the requested language.
+
+Respond with only the translated text. Do not add any notes, explanations, or quotation marks."#;
+
+pub async fn translate(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("translate", &[ "text", "target_language" ], &args);
+    let text = args.require_string("text")?;
+    let target_language = args.require_string("target_language")?;
+
+    let query = format!("Translate this text into {target_language}:\n\n{text}");
+    let response = ask_chatgpt_ephemeral(ctx, TRANSLATE_PROMPT, &query).await?;
+
+    Ok(response.trim().to_string().into())
+}
+
+pub struct TranslateImpl;
+
+#[async_trait]
+impl CommandImpl for TranslateImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        translate(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
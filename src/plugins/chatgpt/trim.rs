@@ -0,0 +1,59 @@
+use std::error::Error;
+
+use async_openai::types::Role;
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+use super::ChatGPTData;
+
+impl ChatGPTData {
+    /// Keeps the system messages at the start of memory and the last `exchanges` exchanges, dropping
+    /// the ones between. An exchange starts at a user message, so what's kept never starts with a
+    /// reply to a dropped message. Returns how many messages were dropped.
+    pub fn trim(&mut self, exchanges: usize) -> usize {
+        let preamble = self.memory.iter()
+            .take_while(|el| el.role == Role::System)
+            .count();
+
+        let starts = self.memory.iter()
+            .enumerate()
+            .skip(preamble)
+            .filter(|(_, el)| el.role == Role::User)
+            .map(|(ind, _)| ind)
+            .collect::<Vec<_>>();
+
+        let keep_from = match exchanges {
+            0 => self.memory.len(),
+            _ if starts.len() <= exchanges => starts.first().cloned().unwrap_or(self.memory.len()),
+            _ => starts[starts.len() - exchanges]
+        };
+
+        let dropped = keep_from - preamble;
+        self.memory.drain(preamble..keep_from);
+        dropped
+    }
+}
+
+pub async fn trim_memory(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("trim_memory", &[ "exchanges" ], &args);
+    let exchanges = args.require_int("exchanges")?.max(0) as usize;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let dropped = invoke::<usize>(chatgpt_info, "trim", exchanges).await?;
+
+    Ok((dropped as i64).into())
+}
+
+pub struct TrimMemoryImpl;
+
+#[async_trait]
+impl CommandImpl for TrimMemoryImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        trim_memory(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
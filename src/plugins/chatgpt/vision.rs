@@ -0,0 +1,129 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{ChatGPTData, ContentFilteredError, EmptyResponseError};
+
+#[derive(Debug, Clone)]
+pub struct VisionApiError(pub String);
+
+impl Display for VisionApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OpenAI could not answer the question about the image. ({})", self.0)
+    }
+}
+
+impl Error for VisionApiError {}
+
+#[derive(Debug, Clone)]
+pub struct VisionUnsupportedError(pub String);
+
+impl Display for VisionUnsupportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "model '{}' does not support vision. Set 'vision model' to a vision-capable model, like gpt-4-vision-preview.", self.0)
+    }
+}
+
+impl Error for VisionUnsupportedError {}
+
+/// Whether an OpenAI model accepts images in its messages.
+pub fn supports_vision(model: &str) -> bool {
+    model.contains("vision") || model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo")
+}
+
+/// A question about an image. The image may be a normal URL, or a `data:` URL holding the image itself.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VisionRequest {
+    pub image_url: String,
+    pub prompt: String
+}
+
+#[derive(Deserialize)]
+struct VisionResponse {
+    choices: Vec<VisionChoice>,
+    usage: Option<VisionUsage>
+}
+
+#[derive(Deserialize)]
+struct VisionChoice {
+    message: VisionMessage,
+    finish_reason: Option<String>
+}
+
+#[derive(Deserialize)]
+struct VisionMessage {
+    content: Option<String>
+}
+
+#[derive(Deserialize)]
+struct VisionUsage {
+    total_tokens: usize
+}
+
+#[derive(Deserialize)]
+struct VisionErrorResponse {
+    error: VisionErrorBody
+}
+
+#[derive(Deserialize)]
+struct VisionErrorBody {
+    message: String
+}
+
+impl ChatGPTData {
+    /// The OpenAI client doesn't support image messages, so vision requests are sent directly.
+    pub async fn respond_vision(&self, request: VisionRequest) -> Result<(String, usize), Box<dyn Error>> {
+        if !supports_vision(&self.vision_model) {
+            return Err(Box::new(VisionUnsupportedError(self.vision_model.clone())));
+        }
+
+        let body = json!({
+            "model": self.vision_model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": request.prompt },
+                        { "type": "image_url", "image_url": { "url": request.image_url } }
+                    ]
+                }
+            ],
+            "max_tokens": 1000,
+            "user": self.user
+        });
+
+        let mut request = self.http
+            .post(format!("{}/chat/completions", self.client_settings.api_base()))
+            .bearer_auth(&self.api_key);
+        if let Some(organization) = &self.client_settings.organization {
+            request = request.header("OpenAI-Organization", organization);
+        }
+        self.throttle.wait().await;
+        let response = request.json(&body).send().await?;
+        self.throttle.record_headers(response.headers());
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<VisionErrorResponse>(&text)
+                .map(|el| el.error.message)
+                .unwrap_or(text);
+            return Err(Box::new(VisionApiError(message)));
+        }
+
+        let response: VisionResponse = serde_json::from_str(&text)?;
+        let tokens = response.usage.map(|el| el.total_tokens).unwrap_or(0);
+
+        let choice = response.choices.into_iter().next().ok_or(EmptyResponseError(self.vision_model.clone(), None))?;
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(Box::new(ContentFilteredError(self.vision_model.clone())));
+        }
+        let content = choice.message.content.unwrap_or_default();
+        if content.trim().is_empty() {
+            return Err(Box::new(EmptyResponseError(self.vision_model.clone(), choice.finish_reason)));
+        }
+
+        Ok((content, tokens))
+    }
+}
@@ -0,0 +1,119 @@
+use std::{error::Error, collections::HashMap, fmt::Display, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, ScriptValue, CommandArgument, HttpRequest, invoke, render_template};
+
+#[derive(Debug, Clone)]
+pub struct CustomCommandNoArgError(pub String, pub String);
+
+impl Display for CustomCommandNoArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the '{}' command did not receive the '{}' argument.", self.0, self.1)
+    }
+}
+
+impl Error for CustomCommandNoArgError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomArgumentConfig {
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "type", default = "default_arg_type")] pub arg_type: String
+}
+
+fn default_arg_type() -> String {
+    "String".to_string()
+}
+
+/// A command declared in the config, which fills its arguments into an HTTP request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomCommandConfig {
+    pub name: String,
+    pub purpose: String,
+    #[serde(default)] pub args: Vec<CustomArgumentConfig>,
+    #[serde(default = "default_method")] pub method: String,
+    pub url: String,
+    #[serde(default)] pub body: Option<String>,
+    #[serde(rename = "return type", default = "default_return_type")] pub return_type: String
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_return_type() -> String {
+    "String".to_string()
+}
+
+fn arg_to_string(value: &ScriptValue) -> Result<String, Box<dyn Error>> {
+    Ok(match value {
+        ScriptValue::String(text) => text.clone(),
+        value => serde_json::to_string(value)?
+    })
+}
+
+pub async fn run_custom_command(ctx: &mut CommandContext, config: &CustomCommandConfig, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    for (ind, arg) in config.args.iter().enumerate() {
+        let value = args.get(ind).ok_or(CustomCommandNoArgError(config.name.clone(), arg.name.clone()))?;
+        values.insert(arg.name.clone(), arg_to_string(value)?);
+    }
+
+    // Values going into the URL are encoded so that they can't break it apart.
+    let url_values = values.iter()
+        .map(|(name, value)| (name.clone(), urlencoding::encode(value).to_string()))
+        .collect::<HashMap<_, _>>();
+    let url = render_template(&config.url, &url_values)?;
+    let body = match &config.body {
+        Some(body) => Some(render_template(body, &values)?),
+        None => None
+    };
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let response = invoke::<String>(browse_info, "send", HttpRequest {
+        method: config.method.clone(),
+        url,
+        body
+    }).await?;
+
+    // JSON responses are passed through as values; anything else is returned as text.
+    Ok(match serde_json::from_str::<ScriptValue>(&response) {
+        Ok(value) => value,
+        Err(_) => response.into()
+    })
+}
+
+pub struct CustomCommandImpl(pub CustomCommandConfig);
+
+#[async_trait]
+impl CommandImpl for CustomCommandImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        run_custom_command(ctx, &self.0, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self(self.0.clone()))
+    }
+}
+
+pub fn create_custom(commands: Vec<CustomCommandConfig>) -> Plugin {
+    Plugin {
+        name: "Custom".to_string(),
+        dependencies: vec![ "Browse".to_string() ],
+        default_timeout: Some(Duration::from_secs(60)),
+        cycle: Box::new(EmptyCycle),
+        commands: commands.into_iter()
+            .map(|config| Command {
+                name: config.name.clone(),
+                purpose: config.purpose.clone(),
+                args: config.args.iter()
+                    .map(|el| CommandArgument::new(&el.name, &el.description, &el.arg_type))
+                    .collect(),
+                return_type: config.return_type.clone(),
+                run: Box::new(CustomCommandImpl(config))
+            })
+            .collect()
+    }
+}
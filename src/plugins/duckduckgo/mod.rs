@@ -0,0 +1,161 @@
+use std::{error::Error, collections::HashMap, time::Duration};
+use async_trait::async_trait;
+use reqwest::Url;
+use select::{document::Document, predicate::{Class, Predicate}};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, invoke, BrowseRequest, PluginData, PluginDataNoInvoke, PluginCycle, ScriptValue, CommandArgument, CommandArgs, Citation, attach_citations, check_config};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuckDuckGoResult {
+    pub title: String,
+    pub link: String,
+    pub snippet: String
+}
+
+/// DuckDuckGo's result links go through a redirect, with the real URL in the `uddg` parameter.
+fn resolve_result_link(href: &str) -> Option<String> {
+    let href = if href.starts_with("//") { format!("https:{href}") } else { href.to_string() };
+    let url = Url::parse(&href).ok()?;
+
+    match url.query_pairs().find(|(key, _)| key == "uddg") {
+        Some((_, target)) => Some(target.to_string()),
+        None => Some(url.to_string())
+    }
+}
+
+/// Reads the results out of DuckDuckGo's HTML-only results page. Ads are skipped.
+pub fn extract_duckduckgo_results(html: &str, max_results: usize) -> Vec<DuckDuckGoResult> {
+    let document = Document::from(html);
+
+    document.find(Class("result").and(Class("result--ad").not()))
+        .filter_map(|result| {
+            let anchor = result.find(Class("result__a")).next()?;
+            let link = resolve_result_link(anchor.attr("href")?)?;
+            let snippet = result.find(Class("result__snippet")).next()
+                .map(|el| el.text())
+                .unwrap_or_default();
+
+            Some(DuckDuckGoResult {
+                title: anchor.text().trim().to_string(),
+                link,
+                snippet: snippet.trim().to_string()
+            })
+        })
+        .take(max_results)
+        .collect()
+}
+
+pub async fn search_duckduckgo(ctx: &mut CommandContext, query: &str) -> Result<Vec<DuckDuckGoResult>, Box<dyn Error>> {
+    let duckduckgo_info = ctx.plugin_data.get_data("DuckDuckGo")?;
+    let max_results = invoke::<usize>(duckduckgo_info, "get max results", true).await?;
+
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let html = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: "https://html.duckduckgo.com/html/".to_string(),
+        params: vec![ ("q".to_string(), query.to_string()) ],
+        basic_auth: None
+    }).await?;
+
+    Ok(extract_duckduckgo_results(&html, max_results))
+}
+
+/// Searches DuckDuckGo, returning the results in the same shape as `google_search`.
+pub async fn duckduckgo_items(ctx: &mut CommandContext, query: &str) -> Result<ScriptValue, Box<dyn Error>> {
+    let items = search_duckduckgo(ctx, query).await?;
+
+    let citations = items.iter()
+        .map(|el| Citation::new(&el.title, &el.link))
+        .collect::<Vec<_>>();
+
+    let value = ScriptValue::Dict(HashMap::from_iter([
+        ("items".to_string(), serde_json::from_value(serde_json::to_value(&items)?)?)
+    ]));
+    Ok(attach_citations(ctx, value, citations)?)
+}
+
+pub async fn duckduckgo(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("duckduckgo_search", &[ "query" ], &args);
+    let query = args.require_string("query")?;
+
+    duckduckgo_items(ctx, &query).await
+}
+
+pub struct DuckDuckGoImpl;
+
+#[async_trait]
+impl CommandImpl for DuckDuckGoImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        duckduckgo(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DuckDuckGoData {
+    #[serde(rename = "max results", default = "default_max_results")] pub max_results: usize
+}
+
+fn default_max_results() -> usize {
+    7
+}
+
+#[async_trait]
+impl PluginData for DuckDuckGoData {
+    async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
+        match name {
+            "get max results" => {
+                Ok(self.max_results.into())
+            }
+            _ => {
+                Err(Box::new(PluginDataNoInvoke("DuckDuckGo".to_string(), name.to_string())))
+            }
+        }
+    }
+}
+
+pub struct DuckDuckGoCycle;
+
+#[async_trait]
+impl PluginCycle for DuckDuckGoCycle {
+    async fn create_context(&self, context: &mut CommandContext, previous_prompt: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: DuckDuckGoData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<DuckDuckGoData>(value)
+    }
+}
+
+pub fn create_duckduckgo() -> Plugin {
+    Plugin {
+        name: "DuckDuckGo".to_string(),
+        dependencies: vec![ "Browse".to_string() ],
+        default_timeout: Some(Duration::from_secs(30)),
+        cycle: Box::new(DuckDuckGoCycle),
+        commands: vec![
+            Command {
+                name: "duckduckgo_search".to_string(),
+                purpose: "DuckDuckGo Search".to_string(),
+                args: vec![
+                    CommandArgument::new("query", "The request to search. Create a short, direct query with keywords.", "String")
+                ],
+                return_type: "{ items: { title: String, link: String, snippet: String }[], citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(DuckDuckGoImpl)
+            }
+        ]
+    }
+}
@@ -0,0 +1,203 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_trait::async_trait;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, ScriptValue, CommandArgument, CommandNoArgError, CommandArgs, cosine_similarity};
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingsUnavailableError(pub String);
+
+impl Display for EmbeddingsUnavailableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "embeddings are not available from the fast agent's model. ({})", self.0)
+    }
+}
+
+impl Error for EmbeddingsUnavailableError {}
+
+pub async fn embed_texts(ctx: &mut CommandContext, texts: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+    let mut embeddings = vec![];
+    for text in texts {
+        let embedding = ctx.agents.fast.llm.model.get_base_embed(text).await
+            .map_err(|err| EmbeddingsUnavailableError(err.to_string()))?;
+        if embedding.len() == 0 {
+            return Err(Box::new(EmbeddingsUnavailableError("the model returned an empty embedding".to_string())));
+        }
+        embeddings.push(embedding);
+    }
+    Ok(embeddings)
+}
+
+fn mean(vectors: &[&Vec<f32>]) -> Vec<f32> {
+    let len = vectors.iter().map(|el| el.len()).max().unwrap_or(0);
+    let mut out = vec![0.; len];
+    for vector in vectors {
+        for (ind, value) in vector.iter().enumerate() {
+            out[ind] += value / vectors.len() as f32;
+        }
+    }
+    out
+}
+
+/// Clusters embeddings with k-means, using cosine similarity as the distance.
+/// Returns the cluster of each embedding.
+pub fn k_means(embeddings: &[Vec<f32>], k: usize, iterations: usize) -> Vec<usize> {
+    let k = k.clamp(1, embeddings.len().max(1));
+
+    // Start from the first item, then keep picking the item least similar to the existing centroids.
+    let mut centroids: Vec<Vec<f32>> = embeddings.iter().take(1).cloned().collect();
+    while centroids.len() < k {
+        let next = embeddings.iter()
+            .min_by(|a, b| {
+                let a = centroids.iter().map(|el| cosine_similarity(a, el)).fold(f32::MIN, f32::max);
+                let b = centroids.iter().map(|el| cosine_similarity(b, el)).fold(f32::MIN, f32::max);
+                a.total_cmp(&b)
+            });
+        match next {
+            Some(next) => centroids.push(next.clone()),
+            None => break
+        }
+    }
+
+    let mut assignments = vec![0; embeddings.len()];
+    for _ in 0..iterations {
+        let new_assignments = embeddings.iter()
+            .map(|embedding| {
+                centroids.iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| cosine_similarity(embedding, a).total_cmp(&cosine_similarity(embedding, b)))
+                    .map(|(ind, _)| ind)
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        let changed = new_assignments != assignments;
+        assignments = new_assignments;
+
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members = embeddings.iter()
+                .zip(&assignments)
+                .filter(|(_, assignment)| **assignment == cluster)
+                .map(|(embedding, _)| embedding)
+                .collect::<Vec<_>>();
+            if members.len() > 0 {
+                *centroid = mean(&members);
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    assignments
+}
+
+pub async fn cluster_texts(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let texts: Vec<ScriptValue> = args.get(0).ok_or(CommandNoArgError("cluster_texts", "texts"))?.clone().try_into()?;
+    let texts = texts.into_iter()
+        .map(|el| el.try_into())
+        .collect::<Result<Vec<String>, _>>()?;
+
+    // Without a requested cluster count, use the common rule of thumb of sqrt(n / 2).
+    let clusters: usize = match args.get(1) {
+        Some(clusters) => TryInto::<i64>::try_into(clusters.clone())?.max(1) as usize,
+        None => ((texts.len() as f64 / 2.).sqrt().round() as usize).max(1)
+    };
+
+    let embeddings = embed_texts(ctx, &texts).await?;
+    let assignments = k_means(&embeddings, clusters, 20);
+
+    let mut out: Vec<ScriptValue> = vec![];
+    for cluster in 0..clusters {
+        let members = (0..texts.len())
+            .filter(|ind| assignments[*ind] == cluster)
+            .collect::<Vec<_>>();
+        if members.len() == 0 {
+            continue;
+        }
+
+        let centroid = mean(&members.iter().map(|ind| &embeddings[*ind]).collect::<Vec<_>>());
+        let representative = members.iter()
+            .max_by(|a, b| cosine_similarity(&embeddings[**a], &centroid).total_cmp(&cosine_similarity(&embeddings[**b], &centroid)))
+            .map(|ind| texts[*ind].clone())
+            .unwrap_or_default();
+
+        out.push(ScriptValue::Dict(HashMap::from_iter([
+            ("representative".to_string(), representative.into()),
+            ("items".to_string(), ScriptValue::List(
+                members.iter().map(|ind| texts[*ind].clone().into()).collect()
+            ))
+        ])));
+    }
+
+    Ok(ScriptValue::List(out))
+}
+
+pub async fn similarity(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("similarity", &[ "first", "second" ], &args);
+    let first = args.require_string("first")?;
+    let second = args.require_string("second")?;
+
+    let embeddings = embed_texts(ctx, &[ first, second ]).await?;
+    let similarity = cosine_similarity(&embeddings[0], &embeddings[1]).clamp(-1., 1.);
+
+    Ok(ScriptValue::Float(similarity as f64))
+}
+
+pub struct ClusterTextsImpl;
+
+#[async_trait]
+impl CommandImpl for ClusterTextsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        cluster_texts(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct SimilarityImpl;
+
+#[async_trait]
+impl CommandImpl for SimilarityImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        similarity(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub fn create_embeddings() -> Plugin {
+    Plugin {
+        name: "Embeddings".to_string(),
+        dependencies: vec![],
+        default_timeout: None,
+        cycle: Box::new(EmptyCycle),
+        commands: vec![
+            Command {
+                name: "cluster_texts".to_string(),
+                purpose: "Group similar texts (like search result snippets) into clusters.".to_string(),
+                args: vec![
+                    CommandArgument::new("texts", "The texts to cluster.", "String[]"),
+                    CommandArgument::optional("clusters", "Optional. How many clusters to make. Chosen automatically if left out.", "Int")
+                ],
+                return_type: "{ representative: String, items: String[] }[]".to_string(),
+                run: Box::new(ClusterTextsImpl)
+            },
+            Command {
+                name: "similarity".to_string(),
+                purpose: "Score how similar two texts are in meaning, from -1 to 1.".to_string(),
+                args: vec![
+                    CommandArgument::new("first", "The first text.", "String"),
+                    CommandArgument::new("second", "The second text.", "String")
+                ],
+                return_type: "Float".to_string(),
+                run: Box::new(SimilarityImpl)
+            }
+        ]
+    }
+}
@@ -142,8 +142,8 @@ impl PluginCycle for FileCycle {
         }))
     }
 
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>> {
-        None
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        Ok(None)
     }
 }
 
@@ -151,6 +151,7 @@ pub fn create_filesystem() -> Plugin {
     Plugin {
         name: "File System".to_string(),
         dependencies: vec![],
+        default_timeout: None,
         cycle: Box::new(FileCycle),
         commands: vec![
             Command {
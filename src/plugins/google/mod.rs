@@ -1,6 +1,8 @@
-use std::{error::Error, backtrace::Backtrace, collections::HashMap, fmt::Display};
+use std::{error::Error, backtrace::Backtrace, collections::HashMap, fmt::Display, time::Duration};
 use async_trait::async_trait;
+use colored::Colorize;
 use reqwest::Client;
+use whatlang::Lang;
 
 mod types;
 
@@ -8,7 +10,7 @@ use serde::{Serialize, Deserialize};
 use serde_json::Value;
 pub use types::*;
 
-use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, invoke, BrowseRequest, PluginData, PluginDataNoInvoke, PluginCycle, ScriptValue, CommandArgument};
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, invoke, BrowseRequest, BrowseData, PluginData, PluginDataNoInvoke, PluginCycle, ScriptValue, CommandArgument, CommandArgs, Citation, attach_citations, check_config, embed_texts, cosine_similarity, duckduckgo_items, detect_text_language, lang_from_iso_639_1, iso_639_1_code};
 
 #[derive(Debug, Clone)]
 pub struct GoogleNoQueryError;
@@ -21,7 +23,131 @@ impl Display for GoogleNoQueryError {
 
 impl Error for GoogleNoQueryError {}
 
-pub async fn google(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+#[derive(Debug, Clone)]
+pub struct MissingEmbeddingsError(pub usize, pub usize);
+
+impl Display for MissingEmbeddingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not rerank results: expected {} usable embeddings, got {}", self.0, self.1)
+    }
+}
+
+impl Error for MissingEmbeddingsError {}
+
+#[derive(Debug, Clone)]
+pub struct UnknownLanguageError(pub String);
+
+impl Display for UnknownLanguageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a known language; use a two-letter code like 'fr'.", self.0)
+    }
+}
+
+impl Error for UnknownLanguageError {}
+
+/// Below this confidence, a result's language is too uncertain to filter it out for.
+const MIN_LANGUAGE_CONFIDENCE: f64 = 0.5;
+
+/// Google's own code for a language, which differs from ISO 639-1 for a few.
+pub fn google_language_code(code: &str) -> String {
+    match code {
+        "zh" => "zh-CN".to_string(),
+        "he" => "iw".to_string(),
+        "nb" => "no".to_string(),
+        code => code.to_string()
+    }
+}
+
+/// The two-letter code of the language a query is confidently written in.
+pub fn detect_query_language(query: &str) -> Option<&'static str> {
+    match detect_text_language(query) {
+        Some((lang, confidence)) if confidence >= MIN_LANGUAGE_CONFIDENCE => iso_639_1_code(lang),
+        _ => None
+    }
+}
+
+/// Drops results whose title and snippet are confidently in another language.
+pub fn filter_items_by_language(items: Vec<Item>, lang: Lang) -> Vec<Item> {
+    items.into_iter()
+        .filter(|item| match detect_text_language(&format!("{}\n{}", item.title, item.snippet)) {
+            Some((detected, confidence)) => detected == lang || confidence < MIN_LANGUAGE_CONFIDENCE,
+            None => true
+        })
+        .collect()
+}
+
+/// Sorts the results by how similar their title and snippet are to the query.
+pub async fn rerank_items(ctx: &mut CommandContext, query: &str, items: &[Item]) -> Result<Vec<Item>, Box<dyn Error>> {
+    let mut texts = vec![ query.to_string() ];
+    texts.extend(items.iter().map(|el| format!("{}\n{}", el.title, el.snippet)));
+
+    // embed_texts fails outright rather than skipping a text, but an embedding that isn't the same
+    // size as the query's can't be compared with it, so it counts as missing.
+    let embeddings = embed_texts(ctx, &texts).await?;
+    let (query_embedding, item_embeddings) = embeddings.split_first()
+        .ok_or(MissingEmbeddingsError(texts.len(), 0))?;
+    let usable = 1 + item_embeddings.iter()
+        .filter(|el| el.len() == query_embedding.len())
+        .count();
+    if usable != texts.len() {
+        return Err(Box::new(MissingEmbeddingsError(texts.len(), usable)));
+    }
+
+    let mut scored = items.iter()
+        .zip(item_embeddings)
+        .map(|(item, embedding)| (item.clone(), cosine_similarity(query_embedding, embedding)))
+        .collect::<Vec<_>>();
+    // The sort is stable, so results that score the same keep Google's order.
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    Ok(scored.into_iter().map(|(item, _)| item).collect())
+}
+
+/// Google's search operators, which make a query narrower.
+const SEARCH_OPERATORS: [&str; 10] = [
+    "site:", "intitle:", "allintitle:", "inurl:", "allinurl:", "intext:", "allintext:", "filetype:", "before:", "after:"
+];
+
+/// Loosens a query that found too little: quotes, parentheses, and `OR`s are dropped, along with
+/// search operators (`site:`, `intitle:`, ...) and excluded (`-word`) or required (`+word`) terms.
+/// The plain keywords that are left are searched as-is.
+pub fn relax_query(query: &str) -> String {
+    query.replace(|c: char| c == '"' || c == '(' || c == ')', " ")
+        .split_whitespace()
+        .filter(|word| {
+            let lower = word.to_lowercase();
+            *word != "OR" && *word != "AND" && !word.starts_with('-')
+                && !SEARCH_OPERATORS.iter().any(|el| lower.starts_with(el))
+        })
+        .map(|word| word.trim_start_matches('+'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Why a Google search came back without a response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoogleSearchFailure {
+    Quota,
+    Unparseable
+}
+
+impl GoogleSearchFailure {
+    /// The message to hand back to the model.
+    pub fn to_value(&self, query: &str) -> ScriptValue {
+        let message = match self {
+            GoogleSearchFailure::Quota => "The Google search quota has been exceeded. Try again later, or use another command.".to_string(),
+            GoogleSearchFailure::Unparseable => format!("Unable to parse your Google request for \"{query}\" Try modifying your query or waiting a bit.")
+        };
+
+        ScriptValue::Dict(HashMap::from_iter([
+            ("error".to_string(), message.into())
+        ]))
+    }
+}
+
+/// Searches Google once, retrying malformed responses.
+pub async fn search_google(ctx: &mut CommandContext, query: &str, language: Option<&str>) -> Result<Result<SearchResponse, GoogleSearchFailure>, Box<dyn Error>> {
     let wolfram_info = ctx.plugin_data.get_data("Google")?;
 
     let api_key = invoke::<String>(wolfram_info, "get api key", true).await?;
@@ -30,40 +156,186 @@ pub async fn google(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<
     let cse_id = invoke::<String>(wolfram_info, "get cse id", true).await?;
     let cse_id: &str = &cse_id;
 
-    let query: String = args.get(0).ok_or(GoogleNoQueryError)?.clone().try_into()?;
+    let parse_retries = invoke::<usize>(wolfram_info, "get parse retries", true).await?;
+    let site_filters = invoke::<SiteFilters>(wolfram_info, "get site filters", true).await?;
 
-    let params = [
-        ("key", api_key),
-        ("cx", cse_id),
-        ("q", &query),
-        ("num", "7")
+    let mut params = vec![
+        ("key".to_string(), api_key.to_string()),
+        ("cx".to_string(), cse_id.to_string()),
+        ("q".to_string(), site_filters.apply_to_query(query)),
+        ("num".to_string(), "7".to_string())
     ];
+    params.extend(site_filters.params());
+    if let Some(language) = language {
+        let code = google_language_code(language);
+        params.push(("lr".to_string(), format!("lang_{code}")));
+        params.push(("hl".to_string(), code));
+    }
     
-    let browse_info = ctx.plugin_data.get_data("Browse")?;
-    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
-        url: "https://www.googleapis.com/customsearch/v1".to_string(),
-        params: params.iter()
-            .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
-    }).await?;
-
-    // The conversion to JSON and from JSON is to get rid of unnecessary properties.
-    let json_result: Result<SearchResponse, serde_json::Error> = serde_json::from_str(&body);
-    let json = match json_result {
-        Ok(json) => {
-            json
+    for attempt in 0..=parse_retries {
+        let request = BrowseRequest {
+            url: "https://www.googleapis.com/customsearch/v1".to_string(),
+            params: params.clone(),
+            basic_auth: None
+        };
+        let browse = ctx.plugin_data.get_data_as::<BrowseData>("Browse")?;
+        browse.wait_for_host(&request.url).await;
+        let bytes = browse.fetch_bytes(request).await?;
+
+        // Deserialized straight from the bytes into the response type, which also gets rid of
+        // unnecessary properties, without building a JSON tree first.
+        match serde_json::from_slice::<GoogleResponseBody>(&bytes) {
+            // Quota errors won't be fixed by asking again, so only retry on malformed responses.
+            Ok(GoogleResponseBody { error: Some(error), .. }) => {
+                if error.is_quota() {
+                    return Ok(Err(GoogleSearchFailure::Quota));
+                }
+                if cfg!(debug_assertions) {
+                    println!("{:?}", error);
+                }
+            }
+            Ok(GoogleResponseBody { items: Some(items), .. }) => {
+                let mut json = SearchResponse { items };
+                json.normalize();
+                return Ok(Ok(json));
+            }
+            Ok(GoogleResponseBody { search_information: Some(_), .. }) => return Ok(Ok(SearchResponse::default())),
+            Ok(_) => {}
+            // A body that isn't JSON at all is malformed, like one with the wrong shape, so it is retried too.
+            Err(err) => {
+                if cfg!(debug_assertions) {
+                    println!("{:?}", err);
+                }
+            }
         }
-        Err(err) => {
-            println!("{:?}", err);
-            println!("{}", body);
-            return Ok(ScriptValue::Dict(HashMap::from_iter([
-                ("error".to_string(), format!("Unable to parse your Google request for \"{query}\" Try modifying your query or waiting a bit.").into())
-            ])));
+
+        if attempt < parse_retries {
+            println!("{} {} / {}", "Retrying Google Search".yellow(), attempt + 1, parse_retries);
         }
+    }
+
+    Ok(Err(GoogleSearchFailure::Unparseable))
+}
+
+/// Runs a full Google search: broadening it if it found too little, re-ranking, and attaching citations.
+/// With a language, Google is asked for results in it, and any in other languages are filtered out.
+pub async fn google_items(ctx: &mut CommandContext, query: &str, language: Option<&str>) -> Result<Result<ScriptValue, GoogleSearchFailure>, Box<dyn Error>> {
+    let lang = match language {
+        Some(language) => Some(lang_from_iso_639_1(language).ok_or(UnknownLanguageError(language.to_string()))?),
+        None => None
+    };
+
+    let google_info = ctx.plugin_data.get_data("Google")?;
+    let rerank = invoke::<bool>(google_info, "get rerank", true).await?;
+    let min_results = invoke::<usize>(google_info, "get min results", true).await?;
+    let relax_empty = invoke::<bool>(google_info, "get relax empty", true).await?;
+
+    let mut json = match search_google(ctx, query, language).await? {
+        Ok(json) => json,
+        Err(failure) => return Ok(Err(failure))
     };
-    let text: String = serde_json::to_string(&json)?;
 
-    Ok(serde_json::from_str(&text)?)
+    // Too few results, or none at all, gets one broader search, which is kept only if it found more.
+    let mut note = None;
+    let empty = json.items.is_empty();
+    if json.items.len() < min_results || (empty && relax_empty) {
+        let relaxed = relax_query(query);
+        if relaxed.len() > 0 && relaxed != query {
+            if let Ok(broader) = search_google(ctx, &relaxed, language).await? {
+                if broader.items.len() > json.items.len() {
+                    json = broader;
+                    if empty {
+                        note = Some(format!("There were no results for \"{query}\", so these are the results for the simplified query \"{relaxed}\"."));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(lang) = lang {
+        json.items = filter_items_by_language(json.items, lang);
+    }
+
+    if rerank {
+        match rerank_items(ctx, query, &json.items).await {
+            Ok(items) => json.items = items,
+            Err(err) => println!("{}: Could not re-rank Google results, keeping Google's order: {}", "Warning".yellow(), err)
+        }
+    }
+
+    let citations = json.items.iter()
+        .map(|el| Citation::new(&el.title, &el.link))
+        .collect::<Vec<_>>();
+
+    let value: ScriptValue = serde_json::from_value(serde_json::to_value(&json)?)?;
+    let mut value = attach_citations(ctx, value, citations)?;
+    if let (ScriptValue::Dict(dict), Some(note)) = (&mut value, note) {
+        dict.insert("note".to_string(), note.into());
+    }
+    Ok(Ok(value))
+}
+
+pub async fn google(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("google_search", &[ "query", "language", "match_language" ], &args);
+    let query = args.require_string("query")?;
+    let mut language = args.optional_string("language")?;
+
+    // An explicit language always wins; otherwise the query's own language may be searched in.
+    let google_info = ctx.plugin_data.get_data("Google")?;
+    let match_language = match args.optional_bool("match_language")? {
+        Some(match_language) => match_language,
+        None => invoke::<bool>(google_info, "get match query language", true).await?
+    };
+    if language.is_none() && match_language {
+        language = detect_query_language(&query).map(|el| el.to_string());
+    }
+
+    match google_items(ctx, &query, language.as_deref()).await? {
+        Ok(value) => Ok(value),
+        Err(failure) => Ok(failure.to_value(&query))
+    }
+}
+
+/// Searches Google, falling back to DuckDuckGo when Google's quota is exhausted and the DuckDuckGo plugin is loaded.
+pub async fn web_search(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("web_search", &[ "query" ], &args);
+    let query = args.require_string("query")?;
+
+    let google_info = ctx.plugin_data.get_data("Google")?;
+    let fallback = invoke::<bool>(google_info, "get duckduckgo fallback", true).await?;
+
+    let failure = match google_items(ctx, &query, None).await? {
+        Ok(value) => return Ok(value),
+        Err(failure) => failure
+    };
+
+    if failure != GoogleSearchFailure::Quota || !fallback || !ctx.plugin_data.0.contains_key("DuckDuckGo") {
+        return Ok(failure.to_value(&query));
+    }
+
+    println!("{}: The Google search quota has been exceeded, searching DuckDuckGo instead.", "Warning".yellow());
+
+    let mut value = duckduckgo_items(ctx, &query).await?;
+    if let ScriptValue::Dict(dict) = &mut value {
+        dict.insert(
+            "note".to_string(),
+            "The Google search quota has been exceeded, so these results are from DuckDuckGo.".to_string().into()
+        );
+    }
+    Ok(value)
+}
+
+pub struct WebSearchImpl;
+
+#[async_trait]
+impl CommandImpl for WebSearchImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        web_search(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
 }
 
 pub struct GoogleImpl;
@@ -82,19 +354,99 @@ impl CommandImpl for GoogleImpl {
 #[derive(Serialize, Deserialize)]
 pub struct GoogleData {
     #[serde(rename = "cse id")] pub cse_id: String,
-    #[serde(rename = "api key")] pub api_key: String
+    #[serde(rename = "api key")] pub api_key: String,
+    #[serde(rename = "parse retries", default = "default_parse_retries")] pub parse_retries: usize,
+    #[serde(default)] pub rerank: bool,
+    #[serde(rename = "include sites", default)] pub include_sites: Vec<String>,
+    #[serde(rename = "exclude sites", default)] pub exclude_sites: Vec<String>,
+    #[serde(rename = "min results", default)] pub min_results: usize,
+    /// Whether a search with no results is tried once more without its quotes and search operators.
+    #[serde(rename = "relax empty", default)] pub relax_empty: bool,
+    #[serde(rename = "duckduckgo fallback", default = "default_duckduckgo_fallback")] pub duckduckgo_fallback: bool,
+    /// Whether google_search looks for results in the language the query is written in, when not given one.
+    #[serde(rename = "match query language", default)] pub match_query_language: bool
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SiteFilters {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>
+}
+
+impl SiteFilters {
+    /// Google's `siteSearch` only takes one site, so several included sites are OR'd into the query instead.
+    pub fn apply_to_query(&self, query: &str) -> String {
+        let mut query = query.to_string();
+        if self.include.len() > 1 {
+            let sites = self.include.iter()
+                .map(|el| format!("site:{el}"))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            query.push_str(&format!(" ({sites})"));
+        }
+        for site in &self.exclude {
+            query.push_str(&format!(" -site:{site}"));
+        }
+        query
+    }
+
+    pub fn params(&self) -> Vec<(String, String)> {
+        match self.include.as_slice() {
+            [ site ] => vec![
+                ("siteSearch".to_string(), site.clone()),
+                ("siteSearchFilter".to_string(), "i".to_string())
+            ],
+            _ => vec![]
+        }
+    }
+}
+
+fn default_parse_retries() -> usize {
+    1
+}
+
+fn default_duckduckgo_fallback() -> bool {
+    true
 }
 
 #[async_trait]
 impl PluginData for GoogleData {
-    async fn apply(&mut self, name: &str, _: Value) -> Result<Value, Box<dyn Error>> {
+    async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
         match name {
             "get api key" => {
                 Ok(self.api_key.clone().into())
             }
+            "set api key" => {
+                self.api_key = serde_json::from_value(value)?;
+                Ok(true.into())
+            }
             "get cse id" => {
                 Ok(self.cse_id.clone().into())
             }
+            "get parse retries" => {
+                Ok(self.parse_retries.into())
+            }
+            "get rerank" => {
+                Ok(self.rerank.into())
+            }
+            "get min results" => {
+                Ok(self.min_results.into())
+            }
+            "get relax empty" => {
+                Ok(self.relax_empty.into())
+            }
+            "get duckduckgo fallback" => {
+                Ok(self.duckduckgo_fallback.into())
+            }
+            "get match query language" => {
+                Ok(self.match_query_language.into())
+            }
+            "get site filters" => {
+                Ok(serde_json::to_value(SiteFilters {
+                    include: self.include_sites.clone(),
+                    exclude: self.exclude_sites.clone()
+                })?)
+            }
             _ => {
                 Err(Box::new(PluginDataNoInvoke("Google".to_string(), name.to_string())))
             }
@@ -110,9 +462,13 @@ impl PluginCycle for GoogleCycle {
         Ok(None)
     }
     
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>> {
-        let data: GoogleData = serde_json::from_value(value).ok()?;
-        Some(Box::new(data))
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: GoogleData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<GoogleData>(value)
     }
 }
 
@@ -120,16 +476,28 @@ pub fn create_google() -> Plugin {
     Plugin {
         name: "Google".to_string(),
         dependencies: vec![ "Browse".to_string() ],
+        default_timeout: Some(Duration::from_secs(30)),
         cycle: Box::new(GoogleCycle),
         commands: vec![
             Command {
                 name: "google_search".to_string(),
                 purpose: "Google Search".to_string(),
                 args: vec![
-                    CommandArgument::new("query", "The request to search. Create a short, direct query with keywords.", "String")
+                    CommandArgument::new("query", "The request to search. Create a short, direct query with keywords.", "String"),
+                    CommandArgument::optional("language", "Optional. A two-letter language code, like \"fr\", to only get results written in that language.", "String"),
+                    CommandArgument::optional("match_language", "Optional. Whether to search in the language the query is written in, when no language is given. Defaults to the config.", "bool")
                 ],
-                return_type: "{ items: { title: String, link: String, snippet: String }[] }".to_string(),
+                return_type: "{ items: { title: String, link: String, snippet: String }[], citations: { title: String, url: String, fetched_at: String }[], note: String | None }".to_string(),
                 run: Box::new(GoogleImpl)
+            },
+            Command {
+                name: "web_search".to_string(),
+                purpose: "Search the web. Uses Google, and DuckDuckGo if Google is unavailable.".to_string(),
+                args: vec![
+                    CommandArgument::new("query", "The request to search. Create a short, direct query with keywords.", "String")
+                ],
+                return_type: "{ items: { title: String, link: String, snippet: String }[], citations: { title: String, url: String, fetched_at: String }[], note: String | None }".to_string(),
+                run: Box::new(WebSearchImpl)
             }
         ]
     }
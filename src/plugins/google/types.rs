@@ -11,6 +11,25 @@ pub struct SearchResponse {
     pub items: Vec<Item>,
 }
 
+impl SearchResponse {
+    /// Tidies the items' whitespace and drops repeated links, keeping the first, so the same results always come out the same.
+    pub fn normalize(&mut self) {
+        let mut links: Vec<String> = vec![];
+        self.items.retain_mut(|item| {
+            item.title = item.title.split_whitespace().collect::<Vec<_>>().join(" ");
+            item.link = item.link.trim().to_string();
+            item.snippet = item.snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+
+            if links.contains(&item.link) {
+                false
+            } else {
+                links.push(item.link.clone());
+                true
+            }
+        });
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Url {
@@ -364,3 +383,47 @@ pub struct Rating {
 pub struct Individualproduct {
     pub name: String,
 }
+
+/// Everything the Custom Search API may send back, so a body is parsed once whatever it holds:
+/// results, an error, or neither (a search with no results has no `items` at all).
+#[derive(Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleResponseBody {
+    #[serde(default)]
+    pub items: Option<Vec<Item>>,
+    #[serde(default)]
+    pub error: Option<GoogleError>,
+    #[serde(default)]
+    pub search_information: Option<serde::de::IgnoredAny>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleErrorResponse {
+    pub error: GoogleError,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub errors: Vec<GoogleErrorDetail>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleErrorDetail {
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl GoogleError {
+    pub fn is_quota(&self) -> bool {
+        self.code == 429 || self.errors.iter().any(|el| {
+            let reason = el.reason.to_ascii_lowercase();
+            reason.contains("ratelimit") || reason.contains("quota") || reason.contains("limitexceeded")
+        })
+    }
+}
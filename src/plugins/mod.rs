@@ -7,6 +7,15 @@ mod wolfram;
 mod chatgpt;
 mod wikipedia;
 mod news;
+mod system;
+mod embeddings;
+mod research;
+mod custom;
+mod text;
+mod prompts;
+mod vision;
+mod pipe;
+mod duckduckgo;
 
 pub use none::*;
 pub use shutdown::*;
@@ -16,4 +25,13 @@ pub use browse::*;
 pub use wolfram::*;
 pub use chatgpt::*;
 pub use news::*;
-pub use wikipedia::*;
\ No newline at end of file
+pub use wikipedia::*;
+pub use system::*;
+pub use embeddings::*;
+pub use research::*;
+pub use custom::*;
+pub use text::*;
+pub use prompts::*;
+pub use vision::*;
+pub use pipe::*;
+pub use duckduckgo::*;
\ No newline at end of file
@@ -8,7 +8,7 @@ use select::{document::Document, predicate::Name};
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 
-use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, invoke, BrowseRequest, PluginDataNoInvoke, PluginData, PluginCycle, ScriptValue, CommandArgument};
+use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, invoke, BrowseRequest, PluginDataNoInvoke, PluginData, PluginCycle, ScriptValue, CommandArgument, check_config};
 
 pub use types::*;
 
@@ -39,7 +39,8 @@ pub async fn ask_news(ctx: &mut CommandContext, query: &str) -> Result<ScriptVal
         url: "https://newsapi.org/v2/everything".to_string(),
         params: params.iter()
             .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>(),
+        basic_auth: None
     }).await?;
 
     let json: News = serde_json::from_str(&json)?;
@@ -95,9 +96,13 @@ impl PluginCycle for NewsCycle {
         Ok(None)
     }
 
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>> {
-        let data: NewsData = serde_json::from_value(value).ok()?;
-        Some(Box::new(data))
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: NewsData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<NewsData>(value)
     }
 }
 
@@ -105,6 +110,7 @@ pub fn create_news() -> Plugin {
     Plugin {
         name: "NewsAPI".to_string(),
         dependencies: vec![ "Browse".to_string() ],
+        default_timeout: None,
         cycle: Box::new(NewsCycle),
         commands: vec![
             Command {
@@ -27,6 +27,7 @@ pub fn create_none() -> Plugin {
     Plugin {
         name: "None".to_string(),
         dependencies: vec![],
+        default_timeout: None,
         cycle: Box::new(EmptyCycle),
         commands: vec![
             Command {
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_trait::async_trait;
+use regex::{Regex, Captures};
+use serde::{Serialize, Deserialize};
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, ScriptValue, CommandArgument, CommandArgs, auto::{dispatch_command, Dispatched}};
+
+#[derive(Debug, Clone)]
+pub struct PipeStepError {
+    pub step: usize,
+    pub command: String,
+    pub message: String,
+    pub completed: Vec<String>
+}
+
+impl Display for PipeStepError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "step {} ('{}') of the pipe failed: {}", self.step, self.command, self.message)?;
+        if self.completed.len() > 0 {
+            write!(f, " Steps that finished first: {}.", self.completed.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PipeStepError {}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PipeStep {
+    pub command: String,
+    #[serde(default)] pub args: Vec<ScriptValue>,
+    /// Lets later steps refer to this one as `{name.output}`, as well as `{stepN.output}`.
+    #[serde(default)] pub name: Option<String>
+}
+
+/// Follows a path like `items.0.link` into a value.
+fn get_path(value: &ScriptValue, path: &[&str]) -> Option<ScriptValue> {
+    let mut value = value;
+    for key in path {
+        value = match value {
+            ScriptValue::Dict(dict) => dict.get(*key)?,
+            ScriptValue::List(list) => list.get(key.parse::<usize>().ok()?)?,
+            _ => return None
+        };
+    }
+    Some(value.clone())
+}
+
+fn stringify(value: &ScriptValue) -> String {
+    match value {
+        ScriptValue::String(string) => string.clone(),
+        value => serde_json::to_string(value).unwrap_or_default()
+    }
+}
+
+/// Replaces `{step.output...}` placeholders in an argument with earlier steps' outputs.
+/// An argument that is only a placeholder becomes the output itself, keeping its type; a placeholder
+/// inside a longer string is written out as text.
+fn substitute(arg: &ScriptValue, outputs: &HashMap<String, ScriptValue>, pattern: &Regex) -> Result<ScriptValue, String> {
+    let lookup = |reference: &str| -> Result<ScriptValue, String> {
+        let mut parts = reference.split('.');
+        let step = parts.next().unwrap_or("");
+        let output = outputs.get(step).ok_or(format!("'{{{reference}}}' refers to a step that hasn't run"))?;
+        let path = parts.collect::<Vec<_>>();
+        match path.split_first() {
+            Some((&"output", rest)) => get_path(output, rest).ok_or(format!("'{{{reference}}}' does not exist in that step's output")),
+            _ => Err(format!("'{{{reference}}}' should look like {{{step}.output}}"))
+        }
+    };
+
+    Ok(match arg {
+        ScriptValue::String(string) => {
+            if let Some(captures) = pattern.captures(string) {
+                if captures[0].len() == string.len() {
+                    return lookup(&captures[1]);
+                }
+            }
+
+            let mut error = None;
+            let replaced = pattern.replace_all(string, |captures: &Captures| {
+                match lookup(&captures[1]) {
+                    Ok(value) => stringify(&value),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                        String::new()
+                    }
+                }
+            }).to_string();
+            if let Some(err) = error {
+                return Err(err);
+            }
+            replaced.into()
+        }
+        ScriptValue::List(list) => ScriptValue::List(
+            list.iter().map(|el| substitute(el, outputs, pattern)).collect::<Result<_, _>>()?
+        ),
+        ScriptValue::Dict(dict) => ScriptValue::Dict(
+            dict.iter().map(|(key, el)| Ok((key.clone(), substitute(el, outputs, pattern)?))).collect::<Result<_, String>>()?
+        ),
+        value => value.clone()
+    })
+}
+
+/// Fits the steps' outputs into what is left of the turn's output budget. They all reach the model
+/// in the pipe's one result, so the earlier outputs are kept whole and the later ones are left out
+/// once the budget runs out, instead of the whole result being cut off partway through.
+fn budget_outputs(results: Vec<ScriptValue>, mut remaining: Option<usize>) -> Vec<ScriptValue> {
+    results.into_iter()
+        .enumerate()
+        .map(|(ind, result)| {
+            let Some(left) = remaining else {
+                return result;
+            };
+            let length = serde_json::to_string(&result).map(|el| el.chars().count()).unwrap_or(0);
+            if length <= left {
+                remaining = Some(left - length);
+                result
+            } else {
+                remaining = Some(0);
+                ScriptValue::String(format!("[Output of step {} left out: the output budget for this turn is used up.]", ind + 1))
+            }
+        })
+        .collect()
+}
+
+pub async fn pipe(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("pipe", &[ "steps" ], &args);
+    let steps: Vec<PipeStep> = args.require_as("steps", "a list of { command, args } steps")?;
+
+    let pattern = Regex::new(r"\{([A-Za-z_][\w-]*(?:\.[\w-]+)+)\}").unwrap();
+    let mut outputs: HashMap<String, ScriptValue> = HashMap::new();
+    let mut results: Vec<ScriptValue> = vec![];
+    let mut completed: Vec<String> = vec![];
+
+    for (ind, step) in steps.iter().enumerate() {
+        let number = ind + 1;
+        let fail = |message: String| PipeStepError {
+            step: number,
+            command: step.command.clone(),
+            message,
+            completed: completed.clone()
+        };
+
+        let command = ctx.commands.iter()
+            .find(|el| el.name == step.command)
+            .map(|el| el.box_clone())
+            .ok_or_else(|| fail("there is no such command.".to_string()))?;
+        let owner = ctx.command_owners.get(&command.name)
+            .cloned()
+            .ok_or_else(|| fail("there is no such command.".to_string()))?;
+
+        let step_args = step.args.iter()
+            .map(|el| substitute(el, &outputs, &pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| fail(format!("{err}.")))?;
+
+        // Steps go through the same dispatcher as the agent's commands, so they are validated, guarded
+        // against recursion, cached, budgeted, and timed out the same way. Their outputs are only shown to
+        // the model as part of the pipe's own result, which is where the output limits apply.
+        let dispatched = dispatch_command(&command.name, &command, &owner.plugin, owner.default_timeout, ctx, &step_args).await
+            .map_err(|err| fail(err.to_string()))?;
+        let result = match dispatched {
+            Dispatched::Finished(result) => result,
+            Dispatched::Stopped(message) => return Err(Box::new(fail(message)))
+        };
+
+        outputs.insert(format!("step{number}"), result.clone());
+        if let Some(name) = &step.name {
+            outputs.insert(name.clone(), result.clone());
+        }
+        completed.push(format!("{number} ('{}')", step.command));
+        results.push(result);
+    }
+
+    // The final output is always kept, and the budget that's left goes to the steps in order.
+    let output = results.last().cloned().unwrap_or(ScriptValue::None);
+    let output_length = serde_json::to_string(&output)?.chars().count();
+    let remaining = ctx.output_budget.remaining().map(|el| el.saturating_sub(output_length));
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("output".to_string(), output),
+        ("outputs".to_string(), ScriptValue::List(budget_outputs(results, remaining)))
+    ])))
+}
+
+pub struct PipeImpl;
+
+#[async_trait]
+impl CommandImpl for PipeImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        pipe(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub fn create_pipe() -> Plugin {
+    Plugin {
+        name: "Pipe".to_string(),
+        dependencies: vec![],
+        default_timeout: None,
+        cycle: Box::new(EmptyCycle),
+        commands: vec![
+            Command {
+                name: "pipe".to_string(),
+                purpose: "Run several commands in order, where a step's args can use earlier outputs, like \"{step1.output}\" or \"{step1.output.items.0.link}\". Stops at the first step that fails.".to_string(),
+                args: vec![
+                    CommandArgument::new("steps", "The commands to run.", "{ command: String, args: any[], name?: String }[]")
+                ],
+                return_type: "{ output: any, outputs: any[] }".to_string(),
+                run: Box::new(PipeImpl)
+            }
+        ]
+    }
+}
@@ -0,0 +1,247 @@
+use std::{collections::HashMap, error::Error, fmt::Display, fs, path::Path};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, ScriptValue, CommandArgument, CommandArgs, PluginData, PluginDataNoInvoke, PluginCycle, invoke, check_config, render_template, ask_chatgpt};
+
+#[derive(Debug, Clone)]
+pub struct NoPromptError(pub String);
+
+impl Display for NoPromptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "there is no saved prompt named '{}'.", self.0)
+    }
+}
+
+impl Error for NoPromptError {}
+
+#[derive(Serialize, Deserialize)]
+pub struct PromptsConfig {
+    #[serde(default = "default_path")] pub path: String
+}
+
+fn default_path() -> String {
+    "prompts.json".to_string()
+}
+
+pub struct PromptsData {
+    pub path: String,
+    pub prompts: HashMap<String, String>
+}
+
+impl PromptsData {
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.prompts)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavePromptRequest {
+    pub name: String,
+    pub template: String
+}
+
+#[async_trait]
+impl PluginData for PromptsData {
+    async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
+        match name {
+            "save" => {
+                let SavePromptRequest { name, template } = serde_json::from_value(value)?;
+                self.prompts.insert(name, template);
+                self.save()?;
+                Ok(true.into())
+            }
+            "get" => {
+                let name: String = serde_json::from_value(value)?;
+                Ok(self.prompts.get(&name).cloned().into())
+            }
+            "list" => {
+                let mut names = self.prompts.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                Ok(names.into())
+            }
+            _ => {
+                Err(Box::new(PluginDataNoInvoke("Prompts".to_string(), name.to_string())))
+            }
+        }
+    }
+}
+
+pub async fn get_template(ctx: &mut CommandContext, name: &str) -> Result<String, Box<dyn Error>> {
+    let prompts_info = ctx.plugin_data.get_data("Prompts")?;
+    let template = invoke::<Option<String>>(prompts_info, "get", name).await?;
+    Ok(template.ok_or(NoPromptError(name.to_string()))?)
+}
+
+pub async fn save_prompt(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("save_prompt", &[ "name", "template" ], &args);
+    let name = args.require_string("name")?;
+    let template = args.require_string("template")?;
+
+    let prompts_info = ctx.plugin_data.get_data("Prompts")?;
+    invoke::<bool>(prompts_info, "save", SavePromptRequest { name, template }).await?;
+
+    Ok(ScriptValue::None)
+}
+
+pub async fn get_prompt(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("get_prompt", &[ "name" ], &args);
+    let name = args.require_string("name")?;
+
+    Ok(get_template(ctx, &name).await?.into())
+}
+
+pub async fn list_prompts(ctx: &mut CommandContext, _args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let prompts_info = ctx.plugin_data.get_data("Prompts")?;
+    let names = invoke::<Vec<String>>(prompts_info, "list", true).await?;
+
+    Ok(ScriptValue::List(names.into_iter().map(|el| el.into()).collect()))
+}
+
+pub async fn render_prompt(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("render_prompt", &[ "name", "variables", "ask" ], &args);
+    let name = args.require_string("name")?;
+    let variables: HashMap<String, String> = args.optional_as("variables", "a map of variable names to strings")?
+        .unwrap_or_default();
+    let ask = args.optional_bool("ask")?.unwrap_or(false);
+
+    let template = get_template(ctx, &name).await?;
+    let prompt = render_template(&template, &variables)?;
+
+    if ask {
+        Ok(ask_chatgpt(ctx, &prompt).await?.into())
+    } else {
+        Ok(prompt.into())
+    }
+}
+
+pub struct SavePromptImpl;
+
+#[async_trait]
+impl CommandImpl for SavePromptImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        save_prompt(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct GetPromptImpl;
+
+#[async_trait]
+impl CommandImpl for GetPromptImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        get_prompt(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct ListPromptsImpl;
+
+#[async_trait]
+impl CommandImpl for ListPromptsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        list_prompts(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct RenderPromptImpl;
+
+#[async_trait]
+impl CommandImpl for RenderPromptImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        render_prompt(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct PromptsCycle;
+
+#[async_trait]
+impl PluginCycle for PromptsCycle {
+    async fn create_context(&self, context: &mut CommandContext, previous_prompt: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let config: PromptsConfig = serde_json::from_value(value)?;
+
+        let prompts = if Path::new(&config.path).exists() {
+            serde_json::from_str(&fs::read_to_string(&config.path)?)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Some(Box::new(PromptsData {
+            path: config.path,
+            prompts
+        })))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<PromptsConfig>(value)
+    }
+}
+
+pub fn create_prompts() -> Plugin {
+    Plugin {
+        name: "Prompts".to_string(),
+        dependencies: vec![],
+        default_timeout: None,
+        cycle: Box::new(PromptsCycle),
+        commands: vec![
+            Command {
+                name: "save_prompt".to_string(),
+                purpose: "Save a prompt template under a name. Use {variable} for parts to fill in later.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name to save the prompt under.", "String"),
+                    CommandArgument::new("template", "The prompt template.", "String")
+                ],
+                return_type: "None".to_string(),
+                run: Box::new(SavePromptImpl)
+            },
+            Command {
+                name: "get_prompt".to_string(),
+                purpose: "Get a saved prompt template.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name of the prompt.", "String")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(GetPromptImpl)
+            },
+            Command {
+                name: "list_prompts".to_string(),
+                purpose: "List the names of every saved prompt.".to_string(),
+                args: vec![],
+                return_type: "String[]".to_string(),
+                run: Box::new(ListPromptsImpl)
+            },
+            Command {
+                name: "render_prompt".to_string(),
+                purpose: "Fill in a saved prompt's variables, and optionally ask ChatGPT the result.".to_string(),
+                args: vec![
+                    CommandArgument::new("name", "The name of the prompt.", "String"),
+                    CommandArgument::optional("variables", "Optional. The values to fill in.", "{ [variable: String]: String }"),
+                    CommandArgument::optional("ask", "Optional. Whether to ask ChatGPT the rendered prompt and return its answer.", "bool")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(RenderPromptImpl)
+            }
+        ]
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, BrowseRequest, PageSection, ChatGPTMessage, ChatGPTRole, EphemeralRequest, EphemeralBatchRequest, ResponseSettings, invoke, attach_citations, parse_model_json, extract_sections_from_html, extract_title_from_html, report_progress};
+
+/// How much page text goes into each request. Longer pages are split into several chunks, and their bullets merged.
+const MAX_CHUNK_CHARS: usize = 8000;
+
+/// What sections before the first heading are called.
+const UNTITLED_SECTION: &str = "Introduction";
+
+const BULLETS_PROMPT: &str = r#"You compress web pages into concise bullet points. The page is split into sections, each starting with "## " and its heading.
+
+Write a few bullet points for the important facts in each section. Each bullet point must name the exact heading of the section it came from.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "bullets": [
+        { "text": "...", "section": "..." }
+    ]
+}"#;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PageBullet {
+    pub text: String,
+    #[serde(default)] pub section: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PageBullets {
+    #[serde(default)] pub bullets: Vec<PageBullet>
+}
+
+/// Groups sections into chunks of at most `MAX_CHUNK_CHARS`, splitting any section too long to fit on its own.
+fn chunk_sections(sections: &[PageSection]) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut chunk = String::new();
+
+    for section in sections {
+        let heading = section.heading.as_deref().unwrap_or(UNTITLED_SECTION);
+        let text = section.text.chars().collect::<Vec<_>>();
+
+        for part in text.chunks(MAX_CHUNK_CHARS) {
+            let part = format!("## {heading}\n{}\n\n", part.iter().collect::<String>());
+            if chunk.len() > 0 && chunk.chars().count() + part.chars().count() > MAX_CHUNK_CHARS {
+                chunks.push(chunk);
+                chunk = String::new();
+            }
+            chunk.push_str(&part);
+        }
+    }
+
+    if chunk.len() > 0 {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+pub async fn bullet_page(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("bullet_page", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    report_progress(ctx, "bullet_page", "Fetching", None);
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let title = extract_title_from_html(&body).unwrap_or(url.clone());
+    let sections = extract_sections_from_html(&body);
+    let chunks = chunk_sections(&sections);
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let concurrency = invoke::<usize>(research_info, "get max concurrency", true).await?;
+    let concurrency = ctx.limit_concurrency(concurrency);
+
+    report_progress(ctx, "bullet_page", "Summarizing", None);
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let responses = invoke::<Vec<Result<String, String>>>(chatgpt_info, "respond ephemeral batch", EphemeralBatchRequest {
+        requests: chunks.into_iter()
+            .map(|chunk| EphemeralRequest {
+                messages: vec![
+                    ChatGPTMessage { role: ChatGPTRole::System, content: BULLETS_PROMPT.to_string() },
+                    ChatGPTMessage { role: ChatGPTRole::User, content: chunk }
+                ],
+                settings: ResponseSettings::default()
+            })
+            .collect(),
+        concurrency
+    }).await?;
+
+    let headings = sections.iter()
+        .map(|el| el.heading.clone().unwrap_or(UNTITLED_SECTION.to_string()))
+        .collect::<Vec<_>>();
+
+    // Chunks are merged in page order. A chunk that fails is skipped, rather than losing the bullets for the rest of the page.
+    let mut bullets: Vec<PageBullet> = vec![];
+    for response in responses.into_iter().filter_map(|el| el.ok()) {
+        let parsed = match parse_model_json::<PageBullets>(ctx, &response).await {
+            Ok(parsed) => parsed,
+            Err(_) => continue
+        };
+
+        for mut bullet in parsed.bullets {
+            bullet.text = bullet.text.trim().to_string();
+            if bullet.text.is_empty() || bullets.iter().any(|el| el.text == bullet.text) {
+                continue;
+            }
+            if !headings.contains(&bullet.section) {
+                if let Some(heading) = headings.iter().find(|el| el.eq_ignore_ascii_case(bullet.section.trim())) {
+                    bullet.section = heading.clone();
+                }
+            }
+            bullets.push(bullet);
+        }
+    }
+
+    let bullets = bullets.into_iter()
+        .map(|el| ScriptValue::Dict(HashMap::from_iter([
+            ("text".to_string(), el.text.into()),
+            ("section".to_string(), el.section.into())
+        ])))
+        .collect::<Vec<_>>();
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(HashMap::from_iter([
+        ("title".to_string(), title.clone().into()),
+        ("url".to_string(), url.clone().into()),
+        ("bullets".to_string(), bullets.into())
+    ])), vec![ Citation::new(&title, &url) ])?)
+}
+
+pub struct BulletPageImpl;
+
+#[async_trait]
+impl CommandImpl for BulletPageImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        bullet_page(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,166 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::{Serialize, Deserialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, invoke};
+
+/// How URLs are normalized when deciding whether two results are the same page.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UrlNormalization {
+    #[serde(rename = "strip trailing slash", default = "default_true")] pub strip_trailing_slash: bool,
+    #[serde(rename = "strip www", default = "default_true")] pub strip_www: bool,
+    #[serde(rename = "ignore scheme", default = "default_true")] pub ignore_scheme: bool,
+    /// Query parameters to drop. A name ending in `*` drops every parameter starting with it.
+    #[serde(rename = "tracking parameters", default = "default_tracking_parameters")] pub tracking_parameters: Vec<String>
+}
+
+impl Default for UrlNormalization {
+    fn default() -> Self {
+        Self {
+            strip_trailing_slash: true,
+            strip_www: true,
+            ignore_scheme: true,
+            tracking_parameters: default_tracking_parameters()
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_tracking_parameters() -> Vec<String> {
+    [ "utm_*", "fbclid", "gclid", "msclkid", "mc_cid", "mc_eid", "ref", "ref_src" ]
+        .iter()
+        .map(|el| el.to_string())
+        .collect()
+}
+
+impl UrlNormalization {
+    fn is_tracking_parameter(&self, name: &str) -> bool {
+        self.tracking_parameters.iter().any(|el| match el.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == el
+        })
+    }
+
+    /// The key two URLs share when they're the same page. URLs that don't parse are compared as-is.
+    pub fn normalize(&self, url: &str) -> String {
+        let mut parsed = match Url::parse(url.trim()) {
+            Ok(parsed) => parsed,
+            Err(_) => return url.trim().to_string()
+        };
+        parsed.set_fragment(None);
+
+        let query = parsed.query_pairs()
+            .filter(|(name, _)| !self.is_tracking_parameter(name))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        if query.len() > 0 {
+            parsed.query_pairs_mut().clear().extend_pairs(query);
+        } else {
+            parsed.set_query(None);
+        }
+
+        let host = parsed.host_str().unwrap_or("").to_lowercase();
+        let host = match self.strip_www {
+            true => host.strip_prefix("www.").unwrap_or(&host).to_string(),
+            false => host
+        };
+        let host = match parsed.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host
+        };
+
+        let mut path = parsed.path().to_string();
+        if self.strip_trailing_slash {
+            path = path.trim_end_matches('/').to_string();
+        }
+
+        let mut key = match self.ignore_scheme {
+            true => format!("{host}{path}"),
+            false => format!("{}://{host}{path}", parsed.scheme())
+        };
+        if let Some(query) = parsed.query() {
+            key.push('?');
+            key.push_str(query);
+        }
+
+        key
+    }
+}
+
+fn get_field<'a>(item: &'a ScriptValue, field: &str) -> Option<&'a str> {
+    match item {
+        ScriptValue::Dict(dict) => match dict.get(field) {
+            Some(ScriptValue::String(value)) => Some(value),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Merges the `items` of several Google results, keeping one result per page.
+/// Of the duplicates, the one with the longest snippet is kept, in the place the page first appeared.
+pub fn merge_search_items(results: Vec<ScriptValue>, normalization: &UrlNormalization) -> Vec<ScriptValue> {
+    let mut keys: Vec<String> = vec![];
+    let mut items: Vec<ScriptValue> = vec![];
+
+    for result in results {
+        // Either a whole Google result, or just its list of items.
+        let result_items = match result {
+            ScriptValue::Dict(mut dict) => dict.remove("items"),
+            list @ ScriptValue::List(_) => Some(list),
+            _ => None
+        };
+
+        if let Some(ScriptValue::List(result_items)) = result_items {
+            for item in result_items {
+                let key = match get_field(&item, "link") {
+                    Some(link) => normalization.normalize(link),
+                    None => continue
+                };
+
+                match keys.iter().position(|el| *el == key) {
+                    Some(ind) => {
+                        let snippet_length = |item: &ScriptValue| get_field(item, "snippet").map(|el| el.trim().len()).unwrap_or(0);
+                        if snippet_length(&item) > snippet_length(&items[ind]) {
+                            items[ind] = item;
+                        }
+                    }
+                    None => {
+                        keys.push(key);
+                        items.push(item);
+                    }
+                }
+            }
+        }
+    }
+
+    items
+}
+
+pub async fn dedupe_results(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("dedupe_results", &[ "result_sets" ], &args);
+    let result_sets: Vec<ScriptValue> = args.require_as("result_sets", "a list of search results")?;
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let normalization = invoke::<UrlNormalization>(research_info, "get url normalization", true).await?;
+
+    Ok(ScriptValue::List(merge_search_items(result_sets, &normalization)))
+}
+
+pub struct DedupeResultsImpl;
+
+#[async_trait]
+impl CommandImpl for DedupeResultsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        dedupe_results(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, error::Error, fmt::Display, fs, path::{Path, PathBuf}};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, ChatGPTMessage, ChatGPTRole, EphemeralRequest, EphemeralBatchRequest, ResponseSettings, invoke, extract_text_from_html, report_progress};
+
+/// How much of a file is summarized at once.
+const MAX_CHUNK_CHARS: usize = 12000;
+
+/// Files larger than this are refused, rather than read into memory.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+const CHUNK_SUMMARY_PROMPT: &str = r#"You summarize documents. You will be given part of a document. Write a one-paragraph summary of it.
+
+Respond with only the summary."#;
+
+const COMBINE_PROMPT: &str = r#"You will be given summaries of consecutive parts of one document. Combine them into a single summary of the whole document.
+
+Respond with only the summary."#;
+
+#[derive(Debug, Clone)]
+pub struct FileNotAllowedError(pub String);
+
+impl Display for FileNotAllowedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not inside one of the Research plugin's 'allowed directories'.", self.0)
+    }
+}
+
+impl Error for FileNotAllowedError {}
+
+#[derive(Debug, Clone)]
+pub struct FileTooLargeError(pub String, pub u64);
+
+impl Display for FileTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is {} bytes, which is too large to summarize.", self.0, self.1)
+    }
+}
+
+impl Error for FileTooLargeError {}
+
+/// Resolves a path, refusing it unless it is inside an allowed directory. Both are canonicalized,
+/// so `..` and symlinks can't be used to reach outside them.
+pub fn resolve_allowed_path(path: &str, allowed_directories: &[String]) -> Result<PathBuf, Box<dyn Error>> {
+    let resolved = Path::new(path).canonicalize()
+        .map_err(|_| FileNotAllowedError(path.to_string()))?;
+
+    let allowed = allowed_directories.iter()
+        .filter_map(|el| Path::new(el).canonicalize().ok())
+        .any(|el| resolved.starts_with(el));
+    if !allowed {
+        return Err(Box::new(FileNotAllowedError(path.to_string())));
+    }
+
+    Ok(resolved)
+}
+
+/// Splits text into chunks at paragraph breaks where it can.
+fn chunk_paragraphs(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut chunk = String::new();
+
+    for paragraph in text.split("\n\n").map(|el| el.trim()).filter(|el| !el.is_empty()) {
+        // A single paragraph longer than a chunk is cut into pieces.
+        let characters = paragraph.chars().collect::<Vec<_>>();
+        for piece in characters.chunks(MAX_CHUNK_CHARS).map(|el| el.iter().collect::<String>()) {
+            if chunk.len() > 0 && chunk.chars().count() + piece.chars().count() > MAX_CHUNK_CHARS {
+                chunks.push(chunk);
+                chunk = String::new();
+            }
+            chunk.push_str(&piece);
+            chunk.push_str("\n\n");
+        }
+    }
+
+    if chunk.len() > 0 {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Asks for a summary, pointed at the question if there is one.
+fn focused_request(prompt: &str, text: String, question: &Option<String>) -> EphemeralRequest {
+    EphemeralRequest {
+        messages: vec![
+            ChatGPTMessage { role: ChatGPTRole::System, content: prompt.to_string() },
+            ChatGPTMessage {
+                role: ChatGPTRole::User,
+                content: match question {
+                    Some(question) => format!("Focus on what it says about: {question}\n\n{text}"),
+                    None => text
+                }
+            }
+        ],
+        settings: ResponseSettings::default()
+    }
+}
+
+/// Sends a batch of requests, keeping the ones that succeed. A part that fails is skipped,
+/// rather than losing the rest of the file.
+async fn respond_batch(ctx: &mut CommandContext, requests: Vec<EphemeralRequest>, concurrency: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let responses = invoke::<Vec<Result<String, String>>>(chatgpt_info, "respond ephemeral batch", EphemeralBatchRequest {
+        requests,
+        concurrency
+    }).await?;
+
+    Ok(responses.into_iter()
+        .filter_map(|el| el.ok())
+        .map(|el| el.trim().to_string())
+        .collect())
+}
+
+/// Groups summaries to be combined, each group fitting in a chunk. Every group but a lone last one
+/// has at least two summaries, so each round has fewer summaries than the last.
+fn group_summaries(summaries: Vec<String>) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = vec![];
+    let mut group: Vec<String> = vec![];
+    let mut group_chars = 0;
+
+    for summary in summaries {
+        let chars = summary.chars().count();
+        if group.len() >= 2 && group_chars + chars > MAX_CHUNK_CHARS {
+            groups.push(group);
+            group = vec![];
+            group_chars = 0;
+        }
+        group_chars += chars;
+        group.push(summary);
+    }
+
+    // A summary left over on its own is folded into the group before it, so that it still gets combined.
+    if group.len() == 1 {
+        if let Some(last) = groups.last_mut() {
+            last.append(&mut group);
+        }
+    }
+    if group.len() > 0 {
+        groups.push(group);
+    }
+    groups
+}
+
+pub async fn summarize_file(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("summarize_file", &[ "path", "question" ], &args);
+    let path = args.require_string("path")?;
+    let question = args.optional_string("question")?;
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let allowed_directories = invoke::<Vec<String>>(research_info, "get allowed directories", true).await?;
+    let concurrency = invoke::<usize>(research_info, "get max concurrency", true).await?;
+    let concurrency = ctx.limit_concurrency(concurrency);
+
+    let resolved = resolve_allowed_path(&path, &allowed_directories)?;
+    let size = fs::metadata(&resolved)?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(Box::new(FileTooLargeError(path, size)));
+    }
+
+    let content = fs::read_to_string(&resolved)?;
+    let is_html = resolved.extension()
+        .and_then(|el| el.to_str())
+        .map_or(false, |el| el.eq_ignore_ascii_case("html") || el.eq_ignore_ascii_case("htm"));
+    let text = match is_html {
+        true => extract_text_from_html(&content),
+        false => content
+    };
+
+    let chunks = chunk_paragraphs(&text);
+    if chunks.len() == 0 {
+        return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("error".to_string(), format!("'{path}' has no text to summarize.").into())
+        ])));
+    }
+
+    report_progress(ctx, "summarize_file", "Summarizing Parts", None);
+    let chunk_count = chunks.len();
+    let requests = chunks.into_iter()
+        .map(|chunk| focused_request(CHUNK_SUMMARY_PROMPT, chunk, &question))
+        .collect();
+    let mut summaries = respond_batch(ctx, requests, concurrency).await?;
+
+    // Summaries are combined a group at a time, so that requests stay around the size of a chunk,
+    // until there is only one left.
+    while summaries.len() > 1 {
+        report_progress(ctx, "summarize_file", "Combining", None);
+        let requests = group_summaries(summaries).into_iter()
+            .map(|group| {
+                let parts = group.iter()
+                    .enumerate()
+                    .map(|(ind, el)| format!("Part {}:\n{}", ind + 1, el))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                focused_request(COMBINE_PROMPT, parts, &question)
+            })
+            .collect();
+        summaries = respond_batch(ctx, requests, concurrency).await?;
+    }
+
+    let Some(summary) = summaries.pop() else {
+        return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("error".to_string(), format!("every request to summarize '{path}' failed.").into())
+        ])));
+    };
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("path".to_string(), resolved.display().to_string().into()),
+        ("summary".to_string(), summary.into()),
+        ("parts".to_string(), ScriptValue::Int(chunk_count as i64))
+    ])))
+}
+
+pub struct SummarizeFileImpl;
+
+#[async_trait]
+impl CommandImpl for SummarizeFileImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        summarize_file(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
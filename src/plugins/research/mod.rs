@@ -0,0 +1,298 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, ScriptValue, CommandArgument, CommandNoArgError, PluginData, PluginDataNoInvoke, PluginCycle, invoke, check_config, ask_chatgpt_ephemeral, google, report_progress};
+
+mod summarize;
+mod verify;
+mod pagediff;
+mod bullets;
+mod dedupe;
+mod rewrite;
+mod video;
+mod file;
+
+pub use summarize::*;
+pub use verify::*;
+pub use pagediff::*;
+pub use bullets::*;
+pub use dedupe::*;
+pub use rewrite::*;
+pub use video::*;
+pub use file::*;
+
+const QUERY_VARIANTS_PROMPT: &str = r#"You rewrite search engine queries. Given a query, write alternative phrasings of it that could find different relevant results.
+
+Respond with only the queries, one per line, with no numbering or extra text."#;
+
+/// Strips list markers like "1.", "-", or "*" that the model sometimes adds anyway.
+fn clean_query_line(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ')' || c == '-' || c == '*')
+        .trim()
+        .trim_matches('"')
+        .to_string()
+}
+
+pub async fn generate_query_variants(ctx: &mut CommandContext, query: &str, count: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let response = ask_chatgpt_ephemeral(ctx, QUERY_VARIANTS_PROMPT, &format!("Write {count} alternative queries for: {query}")).await?;
+
+    let mut queries = vec![ query.to_string() ];
+    for line in response.lines() {
+        let line = clean_query_line(line);
+        if line.len() > 0 && !queries.iter().any(|el| el.eq_ignore_ascii_case(&line)) {
+            queries.push(line);
+        }
+    }
+    queries.truncate(count + 1);
+
+    Ok(queries)
+}
+
+/// Cuts `items` down to how many results `command` may browse, returning a note about the skipped ones if any were.
+pub async fn cap_browsed_results<T>(ctx: &mut CommandContext, command: &str, items: &mut Vec<T>) -> Result<Option<String>, Box<dyn Error>> {
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let cap = invoke::<usize>(research_info, "get max results to browse", command).await?;
+
+    if items.len() <= cap {
+        return Ok(None);
+    }
+
+    let skipped = items.len() - cap;
+    items.truncate(cap);
+    Ok(Some(format!("Stopped early after browsing {cap} results; {skipped} more were skipped.")))
+}
+
+pub async fn expand_search(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let query: String = args.get(0).ok_or(CommandNoArgError("expand_search", "query"))?.clone().try_into()?;
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let count: usize = match args.get(1) {
+        Some(count) => TryInto::<i64>::try_into(count.clone())?.max(1) as usize,
+        None => invoke::<usize>(research_info, "get query variants", true).await?
+    };
+    let search: bool = match args.get(2) {
+        Some(search) => search.clone().try_into()?,
+        None => invoke::<bool>(research_info, "get search variants", true).await?
+    };
+
+    report_progress(ctx, "expand_search", "Generating Queries", None);
+    let queries = generate_query_variants(ctx, &query, count).await?;
+
+    let mut out = HashMap::from_iter([
+        ("queries".to_string(), ScriptValue::List(queries.iter().map(|el| el.clone().into()).collect()))
+    ]);
+
+    if search {
+        let mut results = vec![];
+        for (ind, query) in queries.iter().enumerate() {
+            report_progress(ctx, "expand_search", "Searching", Some((ind + 1, queries.len())));
+            results.push(google(ctx, vec![ query.clone().into() ]).await?);
+        }
+        report_progress(ctx, "expand_search", "Merging Results", None);
+        let research_info = ctx.plugin_data.get_data("Research")?;
+        let normalization = invoke::<UrlNormalization>(research_info, "get url normalization", true).await?;
+        out.insert("items".to_string(), ScriptValue::List(merge_search_items(results, &normalization)));
+    }
+
+    Ok(ScriptValue::Dict(out))
+}
+
+pub struct ExpandSearchImpl;
+
+#[async_trait]
+impl CommandImpl for ExpandSearchImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        expand_search(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ResearchData {
+    #[serde(rename = "query variants", default = "default_query_variants")] pub query_variants: usize,
+    #[serde(rename = "search variants", default = "default_search_variants")] pub search_variants: bool,
+    #[serde(rename = "max concurrency", default = "default_max_concurrency")] pub max_concurrency: usize,
+    #[serde(rename = "max results to browse", default = "default_max_results_to_browse")] pub max_results_to_browse: usize,
+    #[serde(rename = "command max results to browse", default)] pub command_max_results_to_browse: HashMap<String, usize>,
+    #[serde(rename = "url normalization", default)] pub url_normalization: UrlNormalization,
+    /// The directories that summarize_file may read from. Nothing can be read without one.
+    #[serde(rename = "allowed directories", default)] pub allowed_directories: Vec<String>,
+    #[serde(skip)] pub snapshots: HashMap<String, String>
+}
+
+fn default_query_variants() -> usize {
+    3
+}
+
+fn default_search_variants() -> bool {
+    true
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+fn default_max_results_to_browse() -> usize {
+    3
+}
+
+#[async_trait]
+impl PluginData for ResearchData {
+    async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
+        match name {
+            "get query variants" => {
+                Ok(self.query_variants.into())
+            }
+            "get search variants" => {
+                Ok(self.search_variants.into())
+            }
+            "get max concurrency" => {
+                Ok(self.max_concurrency.into())
+            }
+            "get max results to browse" => {
+                let command: String = serde_json::from_value(value)?;
+                let cap = self.command_max_results_to_browse.get(&command).copied()
+                    .unwrap_or(self.max_results_to_browse);
+                Ok(cap.max(1).into())
+            }
+            "get allowed directories" => {
+                Ok(serde_json::to_value(&self.allowed_directories)?)
+            }
+            "get url normalization" => {
+                Ok(serde_json::to_value(&self.url_normalization)?)
+            }
+            "swap snapshot" => {
+                let (url, text): (String, String) = serde_json::from_value(value)?;
+                Ok(self.snapshots.insert(url, text).into())
+            }
+            _ => {
+                Err(Box::new(PluginDataNoInvoke("Research".to_string(), name.to_string())))
+            }
+        }
+    }
+}
+
+pub struct ResearchCycle;
+
+#[async_trait]
+impl PluginCycle for ResearchCycle {
+    async fn create_context(&self, context: &mut CommandContext, previous_prompt: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: ResearchData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<ResearchData>(value)
+    }
+}
+
+pub fn create_research() -> Plugin {
+    Plugin {
+        name: "Research".to_string(),
+        dependencies: vec![ "ChatGPT".to_string(), "Google".to_string(), "Browse".to_string() ],
+        default_timeout: None,
+        cycle: Box::new(ResearchCycle),
+        commands: vec![
+            Command {
+                name: "expand_search".to_string(),
+                purpose: "Rephrase a query into several search variants, and optionally search them all.".to_string(),
+                args: vec![
+                    CommandArgument::new("query", "The query to rephrase.", "String"),
+                    CommandArgument::optional("count", "Optional. How many variants to write.", "Int"),
+                    CommandArgument::optional("search", "Optional. Whether to Google every variant and merge the results.", "bool")
+                ],
+                return_type: "{ queries: String[], items?: { title: String, link: String, snippet: String }[] }".to_string(),
+                run: Box::new(ExpandSearchImpl)
+            },
+            Command {
+                name: "summarize_urls".to_string(),
+                purpose: "Fetch and summarize several pages at once, then combine them into one synthesis.".to_string(),
+                args: vec![
+                    CommandArgument::new("urls", "The URLs to summarize. Only the first few are browsed.", "String[]"),
+                    CommandArgument::optional("question", "Optional. A question to focus the summaries on.", "String")
+                ],
+                return_type: "{ summaries: { url: String, summary?: String, error?: String }[], synthesis: String | None, note?: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(SummarizeUrlsImpl)
+            },
+            Command {
+                name: "verify_claim".to_string(),
+                purpose: "Check a claim against Google results, judging whether they support it, contradict it, or are uncertain.".to_string(),
+                args: vec![
+                    CommandArgument::new("claim", "The claim to check.", "String"),
+                    CommandArgument::optional("results", "Optional. How many of the top results to read. Defaults to, and can't go past, the configured limit.", "Int")
+                ],
+                return_type: "{ verdict: \"supported\" | \"contradicted\" | \"uncertain\", explanation: String, sources: String[], note?: String, citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(VerifyClaimImpl)
+            },
+            Command {
+                name: "diff_pages".to_string(),
+                purpose: "Summarize what changed between two pages, or in one page since the last time it was diffed.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the page.", "String"),
+                    CommandArgument::optional("other_url", "Optional. An older version of the page to compare to. Uses the last snapshot if left out.", "String")
+                ],
+                return_type: "{ changed: bool, summary: String, removed?: Int, added?: Int }".to_string(),
+                run: Box::new(DiffPagesImpl)
+            },
+            Command {
+                name: "bullet_page".to_string(),
+                purpose: "Compress a page into concise bullet points, each marked with the section it came from.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the page.", "String")
+                ],
+                return_type: "{ title: String, url: String, bullets: { text: String, section: String }[], citations: { title: String, url: String, fetched_at: String }[] }".to_string(),
+                run: Box::new(BulletPageImpl)
+            },
+            Command {
+                name: "dedupe_results".to_string(),
+                purpose: "Merge several sets of search results into one list, with each page only once.".to_string(),
+                args: vec![
+                    CommandArgument::new("result_sets", "The search results to merge, each either a google_search result or its list of items.", "({ items: { title: String, link: String, snippet: String }[] } | { title: String, link: String, snippet: String }[])[]")
+                ],
+                return_type: "{ title: String, link: String, snippet: String }[]".to_string(),
+                run: Box::new(DedupeResultsImpl)
+            },
+            Command {
+                name: "search_query".to_string(),
+                purpose: "Turn a question into a short keyword query for searching, and optionally search it.".to_string(),
+                args: vec![
+                    CommandArgument::new("question", "The question to turn into a query.", "String"),
+                    CommandArgument::optional("search", "Optional. Whether to Google the query too. Defaults to false.", "bool")
+                ],
+                return_type: "{ query: String, results?: { items: { title: String, link: String, snippet: String }[], citations: { title: String, url: String, fetched_at: String }[] } }".to_string(),
+                run: Box::new(SearchQueryImpl)
+            },
+            Command {
+                name: "summarize_video".to_string(),
+                purpose: "Summarize a YouTube video from its transcript, with timestamped key points.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the video.", "String"),
+                    CommandArgument::optional("language", "Optional. The language code of the transcript to use. Defaults to \"en\".", "String")
+                ],
+                return_type: "{ title: String, summary: String, language: String, key_points: { timestamp: String | None, point: String }[], citations: { title: String, url: String, fetched_at: String }[] } | { error: String }".to_string(),
+                run: Box::new(SummarizeVideoImpl)
+            },
+            Command {
+                name: "summarize_file".to_string(),
+                purpose: "Summarize a local text or HTML file. Only files in the allowed directories can be read.".to_string(),
+                args: vec![
+                    CommandArgument::new("path", "The path of the file.", "String"),
+                    CommandArgument::optional("question", "Optional. What to focus the summary on.", "String")
+                ],
+                return_type: "{ path: String, summary: String, parts: Int } | { error: String }".to_string(),
+                run: Box::new(SummarizeFileImpl)
+            }
+        ]
+    }
+}
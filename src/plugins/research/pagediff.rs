@@ -0,0 +1,100 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, BrowseRequest, DiffLine, invoke, diff_lines, attach_citations, ask_chatgpt_ephemeral, extract_paragraphs_from_html, extract_title_from_html, report_progress};
+
+/// How many changed lines are shown to ChatGPT, so that a rewritten page doesn't overflow its context.
+const MAX_CHANGED_LINES: usize = 200;
+
+const PAGE_DIFF_PROMPT: &str = r#"You summarize changes to web pages. You will be given the lines that were removed (marked with "-") and added (marked with "+") between two versions of a page.
+
+Summarize what changed in a few sentences. Ignore changes that are only formatting.
+
+Respond with only the summary."#;
+
+async fn fetch_page_text(ctx: &mut CommandContext, url: &str) -> Result<(String, String), Box<dyn Error>> {
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let body = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: url.to_string(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    let title = extract_title_from_html(&body).unwrap_or(url.to_string());
+    Ok((title, extract_paragraphs_from_html(&body).join("\n")))
+}
+
+pub async fn diff_pages(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("diff_pages", &[ "url", "other_url" ], &args);
+    let url = args.require_string("url")?;
+    let other_url = args.optional_string("other_url")?;
+
+    report_progress(ctx, "diff_pages", "Fetching", None);
+    let (title, new_text) = fetch_page_text(ctx, &url).await?;
+
+    // Without a second URL, the page is compared to its snapshot from the last time it was diffed.
+    let old_text = match &other_url {
+        Some(other_url) => {
+            let (_, old_text) = fetch_page_text(ctx, other_url).await?;
+            Some(old_text)
+        }
+        None => {
+            let research_info = ctx.plugin_data.get_data("Research")?;
+            let snapshot = invoke::<Option<String>>(research_info, "swap snapshot", (&url, &new_text)).await?;
+            snapshot
+        }
+    };
+
+    let old_text = match old_text {
+        Some(old_text) => old_text,
+        None => return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("changed".to_string(), false.into()),
+            ("summary".to_string(), "There was no earlier snapshot of this page, so one was saved. Run this again later to see what changed.".to_string().into())
+        ])))
+    };
+
+    let changes = diff_lines(&old_text, &new_text).into_iter()
+        .filter(|el| !matches!(el, DiffLine::Same(_)))
+        .collect::<Vec<_>>();
+
+    if changes.len() == 0 {
+        return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("changed".to_string(), false.into()),
+            ("summary".to_string(), "The page's text has not changed.".to_string().into())
+        ])));
+    }
+
+    report_progress(ctx, "diff_pages", "Summarizing Changes", None);
+    let marked = changes.iter()
+        .take(MAX_CHANGED_LINES)
+        .map(|el| el.to_marked())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary = ask_chatgpt_ephemeral(ctx, PAGE_DIFF_PROMPT, &marked).await?;
+
+    let mut citations = vec![ Citation::new(&title, &url) ];
+    if let Some(other_url) = &other_url {
+        citations.push(Citation::new(other_url, other_url));
+    }
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(HashMap::from_iter([
+        ("changed".to_string(), true.into()),
+        ("summary".to_string(), summary.trim().to_string().into()),
+        ("removed".to_string(), (changes.iter().filter(|el| matches!(el, DiffLine::Removed(_))).count() as i64).into()),
+        ("added".to_string(), (changes.iter().filter(|el| matches!(el, DiffLine::Added(_))).count() as i64).into())
+    ])), citations)?)
+}
+
+pub struct DiffPagesImpl;
+
+#[async_trait]
+impl CommandImpl for DiffPagesImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        diff_pages(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
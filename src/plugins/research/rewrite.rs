@@ -0,0 +1,54 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, google, ask_chatgpt_ephemeral};
+
+const SEARCH_QUERY_PROMPT: &str = r#"You write search engine queries. Turn the question the user sends you into a short query of the keywords that would find the answer. Leave out filler words, and keep names, quoted phrases, and dates.
+
+Respond with only the query."#;
+
+pub async fn rewrite_search_query(ctx: &mut CommandContext, question: &str) -> Result<String, Box<dyn Error>> {
+    let response = ask_chatgpt_ephemeral(ctx, SEARCH_QUERY_PROMPT, question).await?;
+
+    // Only the first line is the query, in case the model added an explanation anyway.
+    let query = response.lines()
+        .map(|el| el.trim())
+        .find(|el| !el.is_empty())
+        .unwrap_or(question)
+        .trim_start_matches("Query:")
+        .trim();
+    let query = query.strip_prefix('"').and_then(|el| el.strip_suffix('"')).unwrap_or(query);
+
+    Ok(query.to_string())
+}
+
+pub async fn search_query(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("search_query", &[ "question", "search" ], &args);
+    let question = args.require_string("question")?;
+    let search = args.optional_bool("search")?.unwrap_or(false);
+
+    let query = rewrite_search_query(ctx, &question).await?;
+
+    let mut out = HashMap::from_iter([
+        ("query".to_string(), query.clone().into())
+    ]);
+    if search {
+        out.insert("results".to_string(), google(ctx, vec![ query.into() ]).await?);
+    }
+
+    Ok(ScriptValue::Dict(out))
+}
+
+pub struct SearchQueryImpl;
+
+#[async_trait]
+impl CommandImpl for SearchQueryImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        search_query(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
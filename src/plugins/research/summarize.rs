@@ -0,0 +1,149 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, BrowseRequest, BrowseManyRequest, ChatGPTMessage, ChatGPTRole, EphemeralRequest, EphemeralBatchRequest, ResponseSettings, invoke, attach_citations, ask_chatgpt_ephemeral, extract_text_from_html, extract_title_from_html, report_progress};
+
+use super::cap_browsed_results;
+
+/// How much of each page's text is sent to be summarized.
+const MAX_PAGE_CHARS: usize = 12000;
+
+const SUMMARY_PROMPT: &str = r#"You summarize web pages. Write a one-paragraph summary of the page the user sends you.
+
+Respond with only the summary."#;
+
+const SYNTHESIS_PROMPT: &str = r#"You combine summaries of several sources into one synthesis. Point out where the sources agree and where they disagree, and mention sources by their number.
+
+Respond with only the synthesis."#;
+
+pub async fn summarize_urls(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("summarize_urls", &[ "urls", "question" ], &args);
+    let mut urls: Vec<String> = args.require_as("urls", "a list of URLs")?;
+    let question = args.optional_string("question")?;
+
+    let note = cap_browsed_results(ctx, "summarize_urls", &mut urls).await?;
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let concurrency = invoke::<usize>(research_info, "get max concurrency", true).await?;
+    let concurrency = ctx.limit_concurrency(concurrency);
+
+    report_progress(ctx, "summarize_urls", "Fetching Pages", None);
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let pages = invoke::<Vec<Result<String, String>>>(browse_info, "browse many", BrowseManyRequest {
+        requests: urls.iter()
+            .map(|url| BrowseRequest {
+                url: url.clone(),
+                params: vec![],
+                basic_auth: None
+            })
+            .collect(),
+        concurrency
+    }).await?;
+
+    let titles = pages.iter()
+        .zip(&urls)
+        .map(|(page, url)| match page {
+            Ok(body) => extract_title_from_html(body).unwrap_or(url.clone()),
+            Err(_) => url.clone()
+        })
+        .collect::<Vec<_>>();
+
+    // Only pages that were fetched are summarized; the rest keep their fetch error.
+    let fetched = pages.iter()
+        .enumerate()
+        .filter_map(|(ind, page)| page.as_ref().ok().map(|body| (ind, body)))
+        .collect::<Vec<_>>();
+
+    let requests = fetched.iter()
+        .map(|(_, body)| {
+            let text = extract_text_from_html(body).chars().take(MAX_PAGE_CHARS).collect::<String>();
+            let query = match &question {
+                Some(question) => format!("Focus on what the page says about: {question}\n\n{text}"),
+                None => text
+            };
+            EphemeralRequest {
+                messages: vec![
+                    ChatGPTMessage { role: ChatGPTRole::System, content: SUMMARY_PROMPT.to_string() },
+                    ChatGPTMessage { role: ChatGPTRole::User, content: query }
+                ],
+                settings: ResponseSettings::default()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    report_progress(ctx, "summarize_urls", "Summarizing Pages", None);
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let summaries = invoke::<Vec<Result<String, String>>>(chatgpt_info, "respond ephemeral batch", EphemeralBatchRequest {
+        requests,
+        concurrency
+    }).await?;
+
+    let mut results: Vec<Result<String, String>> = pages.into_iter()
+        .map(|page| page.map(|_| String::new()))
+        .collect();
+    for ((ind, _), summary) in fetched.iter().zip(summaries) {
+        results[*ind] = summary.map(|el| el.trim().to_string());
+    }
+
+    let successes = results.iter()
+        .enumerate()
+        .filter_map(|(ind, result)| result.as_ref().ok().map(|summary| (ind, summary)))
+        .collect::<Vec<_>>();
+
+    let synthesis = if successes.len() > 0 {
+        report_progress(ctx, "summarize_urls", "Synthesizing", None);
+        let sources = successes.iter()
+            .map(|(ind, summary)| format!("Source {} ({}):\n{}", ind + 1, titles[*ind], summary))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let query = match &question {
+            Some(question) => format!("Answer this question using the sources: {question}\n\n{sources}"),
+            None => sources
+        };
+        ask_chatgpt_ephemeral(ctx, SYNTHESIS_PROMPT, &query).await?.trim().to_string().into()
+    } else {
+        ScriptValue::None
+    };
+
+    let citations = successes.iter()
+        .map(|(ind, _)| Citation::new(&titles[*ind], &urls[*ind]))
+        .collect::<Vec<_>>();
+
+    let summaries = results.into_iter()
+        .zip(&urls)
+        .map(|(result, url)| {
+            let mut dict = HashMap::from_iter([
+                ("url".to_string(), url.clone().into())
+            ]);
+            match result {
+                Ok(summary) => dict.insert("summary".to_string(), summary.into()),
+                Err(err) => dict.insert("error".to_string(), err.into())
+            };
+            ScriptValue::Dict(dict)
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = HashMap::from_iter([
+        ("summaries".to_string(), summaries.into()),
+        ("synthesis".to_string(), synthesis)
+    ]);
+    if let Some(note) = note {
+        out.insert("note".to_string(), note.into());
+    }
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(out), citations)?)
+}
+
+pub struct SummarizeUrlsImpl;
+
+#[async_trait]
+impl CommandImpl for SummarizeUrlsImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        summarize_urls(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
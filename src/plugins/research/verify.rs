@@ -0,0 +1,145 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, BrowseRequest, BrowseManyRequest, invoke, google, attach_citations, ask_chatgpt_ephemeral, parse_model_json, extract_text_from_html, report_progress};
+
+use super::cap_browsed_results;
+
+/// How much of each source's text is given to the judge.
+const MAX_SOURCE_CHARS: usize = 6000;
+
+const VERIFY_PROMPT: &str = r#"You are a fact checker. You will be given a claim and numbered sources. Judge whether the sources support the claim, contradict it, or are uncertain about it.
+
+Only use the sources, not your own knowledge.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "verdict": "supported" | "contradicted" | "uncertain",
+    "explanation": "...",
+    "sources": [ 1, 2 ]
+}
+
+"sources" lists the numbers of the sources your verdict is based on."#;
+
+#[derive(Serialize, Deserialize)]
+pub struct ClaimJudgement {
+    pub verdict: String,
+    #[serde(default)] pub explanation: String,
+    #[serde(default)] pub sources: Vec<usize>
+}
+
+pub async fn verify_claim(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("verify_claim", &[ "claim", "results" ], &args);
+    let claim = args.require_string("claim")?;
+    let results = args.optional_int("results")?.map(|el| el.max(1) as usize);
+
+    report_progress(ctx, "verify_claim", "Searching", None);
+    let search = google(ctx, vec![ claim.clone().into() ]).await?;
+    let items = match search {
+        ScriptValue::Dict(mut dict) => match dict.remove("items") {
+            Some(ScriptValue::List(items)) => items,
+            _ => vec![]
+        },
+        _ => vec![]
+    };
+
+    let mut sources = items.iter()
+        .filter_map(|item| match item {
+            ScriptValue::Dict(dict) => match (dict.get("title"), dict.get("link")) {
+                (Some(ScriptValue::String(title)), Some(ScriptValue::String(link))) => Some((title.clone(), link.clone())),
+                _ => None
+            },
+            _ => None
+        })
+        .take(results.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+
+    // Only a note when the limit cut short what was asked for, not when Google simply had more results.
+    let note = cap_browsed_results(ctx, "verify_claim", &mut sources).await?
+        .filter(|_| results.is_some());
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let concurrency = invoke::<usize>(research_info, "get max concurrency", true).await?;
+    let concurrency = ctx.limit_concurrency(concurrency);
+
+    report_progress(ctx, "verify_claim", "Reading Sources", None);
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let pages = invoke::<Vec<Result<String, String>>>(browse_info, "browse many", BrowseManyRequest {
+        requests: sources.iter()
+            .map(|(_, link)| BrowseRequest {
+                url: link.clone(),
+                params: vec![],
+                basic_auth: None
+            })
+            .collect(),
+        concurrency
+    }).await?;
+
+    let read = sources.iter()
+        .zip(pages)
+        .filter_map(|(source, page)| page.ok().map(|body| (source.clone(), extract_text_from_html(&body))))
+        .collect::<Vec<_>>();
+
+    if read.len() == 0 {
+        return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("verdict".to_string(), "uncertain".to_string().into()),
+            ("explanation".to_string(), "No sources could be found or read for this claim.".to_string().into()),
+            ("sources".to_string(), ScriptValue::List(vec![]))
+        ])));
+    }
+
+    report_progress(ctx, "verify_claim", "Judging", None);
+    let query = format!(
+        "Claim: {claim}\n\n{}",
+        read.iter()
+            .enumerate()
+            .map(|(ind, ((title, _), text))| format!(
+                "Source {} ({title}):\n{}", ind + 1, text.chars().take(MAX_SOURCE_CHARS).collect::<String>()
+            ))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    );
+    let response = ask_chatgpt_ephemeral(ctx, VERIFY_PROMPT, &query).await?;
+    let judgement: ClaimJudgement = parse_model_json(ctx, &response).await?;
+
+    let verdict = match judgement.verdict.to_lowercase().as_str() {
+        "supported" => "supported",
+        "contradicted" => "contradicted",
+        _ => "uncertain"
+    };
+
+    let cited = judgement.sources.iter()
+        .filter_map(|ind| ind.checked_sub(1).and_then(|ind| read.get(ind)))
+        .map(|((title, link), _)| (title.clone(), link.clone()))
+        .collect::<Vec<_>>();
+    let citations = cited.iter()
+        .map(|(title, link)| Citation::new(title, link))
+        .collect::<Vec<_>>();
+
+    let mut out = HashMap::from_iter([
+        ("verdict".to_string(), verdict.to_string().into()),
+        ("explanation".to_string(), judgement.explanation.into()),
+        ("sources".to_string(), ScriptValue::List(cited.into_iter().map(|(_, link)| link.into()).collect()))
+    ]);
+    if let Some(note) = note {
+        out.insert("note".to_string(), note.into());
+    }
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(out), citations)?)
+}
+
+pub struct VerifyClaimImpl;
+
+#[async_trait]
+impl CommandImpl for VerifyClaimImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        verify_claim(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
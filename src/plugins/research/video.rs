@@ -0,0 +1,259 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use async_trait::async_trait;
+use reqwest::Url;
+use select::{document::Document, predicate::Name};
+use serde::{Serialize, Deserialize};
+
+use crate::{CommandContext, CommandImpl, CommandArgs, ScriptValue, Citation, BrowseRequest, ChatGPTMessage, ChatGPTRole, EphemeralRequest, EphemeralBatchRequest, ResponseSettings, invoke, attach_citations, ask_chatgpt_ephemeral, parse_model_json, extract_title_from_html, report_progress};
+
+/// How much transcript goes into each request. Longer transcripts are split, and their key points merged.
+const MAX_CHUNK_CHARS: usize = 8000;
+
+const KEY_POINTS_PROMPT: &str = r#"You summarize video transcripts. Each line of the transcript starts with its timestamp in brackets.
+
+Write the key points made in the transcript, each with the timestamp of the line where it's made.
+
+Respond in exactly this JSON format, with nothing else:
+
+{
+    "key_points": [
+        { "timestamp": "...", "point": "..." }
+    ]
+}"#;
+
+const SUMMARY_PROMPT: &str = "You summarize videos. The user sends you the key points of a video, in order. Write a one-paragraph summary of the video.";
+
+#[derive(Debug, Clone)]
+pub struct NotAVideoError(pub String);
+
+impl Display for NotAVideoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a YouTube video URL.", self.0)
+    }
+}
+
+impl Error for NotAVideoError {}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeyPoint {
+    #[serde(default)] pub timestamp: Option<String>,
+    pub point: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeyPoints {
+    #[serde(default)] pub key_points: Vec<KeyPoint>
+}
+
+#[derive(Deserialize)]
+struct CaptionTrack {
+    #[serde(rename = "baseUrl")] base_url: String,
+    #[serde(rename = "languageCode", default)] language_code: String,
+    #[serde(default)] kind: Option<String>
+}
+
+pub struct TranscriptLine {
+    pub start: f64,
+    pub text: String
+}
+
+/// Finds the video id in a `watch?v=`, `youtu.be/`, `shorts/`, or `embed/` URL.
+pub fn extract_video_id(url: &str) -> Option<String> {
+    let url = Url::parse(url.trim()).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.").trim_start_matches("m.");
+    let segments = url.path_segments()?.collect::<Vec<_>>();
+
+    let id = match (host, segments.as_slice()) {
+        ("youtu.be", [ id, .. ]) => id.to_string(),
+        ("youtube.com", [ "watch" ]) => url.query_pairs().find(|(key, _)| key == "v")?.1.to_string(),
+        ("youtube.com", [ "shorts" | "embed" | "live", id, .. ]) => id.to_string(),
+        _ => return None
+    };
+
+    match id.len() > 0 {
+        true => Some(id),
+        false => None
+    }
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` past an hour.
+pub fn format_timestamp(seconds: f64) -> String {
+    let seconds = seconds.max(0.) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    match hours {
+        0 => format!("{minutes}:{seconds:02}"),
+        _ => format!("{hours}:{minutes:02}:{seconds:02}")
+    }
+}
+
+/// Reads the caption tracks out of the player data embedded in a watch page.
+fn extract_caption_tracks(html: &str) -> Vec<CaptionTrack> {
+    let start = match html.find("\"captionTracks\":") {
+        Some(start) => start + "\"captionTracks\":".len(),
+        None => return vec![]
+    };
+
+    // Only the array itself is parsed, ignoring the rest of the page after it.
+    serde_json::Deserializer::from_str(&html[start..])
+        .into_iter::<Vec<CaptionTrack>>()
+        .next()
+        .and_then(|el| el.ok())
+        .unwrap_or_default()
+}
+
+/// Picks a track in the language, preferring human-written captions over generated ones.
+fn choose_track<'a>(tracks: &'a [CaptionTrack], language: &str) -> Option<&'a CaptionTrack> {
+    let in_language = |el: &&CaptionTrack| el.language_code.split('-').next() == Some(language);
+    let generated = |el: &&CaptionTrack| el.kind.as_deref() == Some("asr");
+
+    tracks.iter().find(|el| in_language(el) && !generated(el))
+        .or_else(|| tracks.iter().find(in_language))
+        .or_else(|| tracks.first())
+}
+
+/// Parses a timed-text transcript. Its text is escaped twice, so entities are decoded again after parsing.
+pub fn parse_transcript(xml: &str) -> Vec<TranscriptLine> {
+    let document = Document::from(xml);
+
+    document.find(Name("text"))
+        .filter_map(|el| {
+            let start = el.attr("start")?.parse::<f64>().ok()?;
+            let text = el.text()
+                .replace("&#39;", "'")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            match text.is_empty() {
+                true => None,
+                false => Some(TranscriptLine { start, text })
+            }
+        })
+        .collect()
+}
+
+fn chunk_transcript(lines: &[TranscriptLine]) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut chunk = String::new();
+
+    for line in lines {
+        let line = format!("[{}] {}\n", format_timestamp(line.start), line.text);
+        if chunk.len() > 0 && chunk.chars().count() + line.chars().count() > MAX_CHUNK_CHARS {
+            chunks.push(chunk);
+            chunk = String::new();
+        }
+        chunk.push_str(&line);
+    }
+
+    if chunk.len() > 0 {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+pub async fn summarize_video(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("summarize_video", &[ "url", "language" ], &args);
+    let url = args.require_string("url")?;
+    let language = args.optional_string("language")?.unwrap_or("en".to_string());
+
+    let id = extract_video_id(&url).ok_or_else(|| NotAVideoError(url.clone()))?;
+    let watch_url = format!("https://www.youtube.com/watch?v={id}");
+
+    report_progress(ctx, "summarize_video", "Fetching Transcript", None);
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let page = invoke::<String>(browse_info, "browse", BrowseRequest {
+        url: watch_url.clone(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+    let title = extract_title_from_html(&page).unwrap_or(watch_url.clone());
+
+    // The track can be in another language when there's none in the one asked for, so the result
+    // says which one was used.
+    let tracks = extract_caption_tracks(&page);
+    let track = choose_track(&tracks, &language);
+    let track_language = track.map(|el| el.language_code.clone());
+    let lines = match track {
+        Some(track) => {
+            let browse_info = ctx.plugin_data.get_data("Browse")?;
+            let xml = invoke::<String>(browse_info, "browse", BrowseRequest {
+                url: track.base_url.clone(),
+                params: vec![],
+                basic_auth: None
+            }).await?;
+            parse_transcript(&xml)
+        }
+        None => vec![]
+    };
+
+    if lines.len() == 0 {
+        return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("error".to_string(), format!("The video \"{title}\" has no transcript, so it can't be summarized.").into())
+        ])));
+    }
+
+    let research_info = ctx.plugin_data.get_data("Research")?;
+    let concurrency = invoke::<usize>(research_info, "get max concurrency", true).await?;
+    let concurrency = ctx.limit_concurrency(concurrency);
+
+    report_progress(ctx, "summarize_video", "Finding Key Points", None);
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let responses = invoke::<Vec<Result<String, String>>>(chatgpt_info, "respond ephemeral batch", EphemeralBatchRequest {
+        requests: chunk_transcript(&lines).into_iter()
+            .map(|chunk| EphemeralRequest {
+                messages: vec![
+                    ChatGPTMessage { role: ChatGPTRole::System, content: KEY_POINTS_PROMPT.to_string() },
+                    ChatGPTMessage { role: ChatGPTRole::User, content: chunk }
+                ],
+                settings: ResponseSettings::default()
+            })
+            .collect(),
+        concurrency
+    }).await?;
+
+    // Chunks are merged in transcript order. A chunk that fails is skipped, rather than losing the rest of the video.
+    let mut key_points: Vec<KeyPoint> = vec![];
+    for response in responses.into_iter().filter_map(|el| el.ok()) {
+        if let Ok(parsed) = parse_model_json::<KeyPoints>(ctx, &response).await {
+            key_points.extend(parsed.key_points.into_iter().filter(|el| !el.point.trim().is_empty()));
+        }
+    }
+
+    report_progress(ctx, "summarize_video", "Summarizing", None);
+    let outline = key_points.iter()
+        .map(|el| format!("- {}", el.point))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary = ask_chatgpt_ephemeral(ctx, SUMMARY_PROMPT, &outline).await?;
+
+    let key_points = key_points.into_iter()
+        .map(|el| ScriptValue::Dict(HashMap::from_iter([
+            ("timestamp".to_string(), el.timestamp.map(|el| el.into()).unwrap_or(ScriptValue::None)),
+            ("point".to_string(), el.point.into())
+        ])))
+        .collect::<Vec<_>>();
+
+    Ok(attach_citations(ctx, ScriptValue::Dict(HashMap::from_iter([
+        ("title".to_string(), title.clone().into()),
+        ("summary".to_string(), summary.trim().to_string().into()),
+        ("language".to_string(), track_language.unwrap_or(language).into()),
+        ("key_points".to_string(), key_points.into())
+    ])), vec![ Citation::new(&title, &watch_url) ])?)
+}
+
+pub struct SummarizeVideoImpl;
+
+#[async_trait]
+impl CommandImpl for SummarizeVideoImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        summarize_video(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
@@ -41,6 +41,7 @@ pub fn create_shutdown() -> Plugin {
     Plugin {
         name: "Shutdown".to_string(),
         dependencies: vec![],
+        default_timeout: None,
         cycle: Box::new(EmptyCycle),
         commands: vec![
             Command {
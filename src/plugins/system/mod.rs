@@ -0,0 +1,174 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::{Arc, Mutex}};
+
+use async_trait::async_trait;
+use chrono::{Local, Utc};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use tokio::runtime::Runtime;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, ScriptValue, list_plugins, PluginData, PluginDataNoInvoke, PluginCycle, invoke, check_config};
+
+#[derive(Debug, Clone)]
+pub struct UnknownTimezoneError(pub String);
+
+impl Display for UnknownTimezoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a known timezone; use an IANA name like 'America/New_York'.", self.0)
+    }
+}
+
+impl Error for UnknownTimezoneError {}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, UnknownTimezoneError> {
+    timezone.parse::<Tz>().map_err(|_| UnknownTimezoneError(timezone.to_string()))
+}
+
+pub async fn validate_config(ctx: &mut CommandContext, _: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let mut results: HashMap<String, ScriptValue> = HashMap::new();
+
+    for plugin in list_plugins() {
+        let plugin_config = ctx.plugin_configs.iter()
+            .find(|(name, _)| name.to_ascii_lowercase() == plugin.name.to_ascii_lowercase());
+
+        if let Some((_, plugin_config)) = plugin_config {
+            let result = match plugin.cycle.validate_config(plugin_config.clone()) {
+                Ok(()) => "ok".to_string(),
+                Err(err) => format!("error: {err}")
+            };
+
+            results.insert(plugin.name.clone(), result.into());
+        }
+    }
+
+    Ok(ScriptValue::Dict(results))
+}
+
+/// Replaces a plugin's API key. This is for the operator, not the model: it isn't a command,
+/// so the key never passes through command arguments, results, or the transcript.
+/// The plugin's data is only reachable through the locked `CommandContext`, so no command
+/// can observe a half-rotated key.
+pub fn rotate_api_key(context: &Arc<Mutex<CommandContext>>, plugin: &str, key: &str) -> Result<(), Box<dyn Error>> {
+    let mut context = context.lock().unwrap();
+    let plugin_info = context.plugin_data.get_data(plugin)?;
+
+    let runtime = Runtime::new()?;
+    runtime.block_on(invoke::<bool>(plugin_info, "set api key", key))?;
+
+    Ok(())
+}
+
+/// The current time in UTC and in the configured timezone, or the machine's own if there isn't one.
+pub async fn current_time(ctx: &mut CommandContext, _: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let system_info = ctx.plugin_data.get_data("System")?;
+    let timezone = invoke::<Option<String>>(system_info, "get timezone", true).await?;
+
+    let now = Utc::now();
+    let (local, timezone) = match timezone {
+        Some(timezone) => (now.with_timezone(&parse_timezone(&timezone)?).to_rfc3339(), timezone),
+        None => (now.with_timezone(&Local).to_rfc3339(), "local".to_string())
+    };
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("utc".to_string(), now.to_rfc3339().into()),
+        ("local".to_string(), local.into()),
+        ("timezone".to_string(), timezone.into()),
+        ("unix".to_string(), now.timestamp().into())
+    ])))
+}
+
+pub struct ValidateConfigImpl;
+
+#[async_trait]
+impl CommandImpl for ValidateConfigImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        validate_config(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct CurrentTimeImpl;
+
+#[async_trait]
+impl CommandImpl for CurrentTimeImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        current_time(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SystemData {
+    /// An IANA timezone, like 'Europe/Paris', for `current_time`'s local time.
+    #[serde(default)] pub timezone: Option<String>
+}
+
+#[async_trait]
+impl PluginData for SystemData {
+    async fn apply(&mut self, name: &str, value: Value) -> Result<Value, Box<dyn Error>> {
+        match name {
+            "get timezone" => {
+                Ok(self.timezone.clone().into())
+            }
+            _ => {
+                Err(Box::new(PluginDataNoInvoke("System".to_string(), name.to_string())))
+            }
+        }
+    }
+}
+
+pub struct SystemCycle;
+
+#[async_trait]
+impl PluginCycle for SystemCycle {
+    async fn create_context(&self, context: &mut CommandContext, previous_prompt: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: SystemData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<SystemData>(value.clone())?;
+
+        let data: SystemData = serde_json::from_value(value)?;
+        if let Some(timezone) = &data.timezone {
+            parse_timezone(timezone)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn create_system() -> Plugin {
+    Plugin {
+        name: "System".to_string(),
+        dependencies: vec![],
+        default_timeout: None,
+        cycle: Box::new(SystemCycle),
+        commands: vec![
+            Command {
+                name: "validate_config".to_string(),
+                purpose: "Check every plugin's configuration for missing or misnamed fields.".to_string(),
+                args: vec![],
+                return_type: "{ [plugin: String]: String }".to_string(),
+                run: Box::new(ValidateConfigImpl)
+            },
+            Command {
+                name: "current_time".to_string(),
+                purpose: "Get the current date and time, in UTC and in the local timezone.".to_string(),
+                args: vec![],
+                return_type: "{ utc: String, local: String, timezone: String, unix: Int }".to_string(),
+                run: Box::new(CurrentTimeImpl)
+            }
+        ]
+    }
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use whatlang::Lang;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, ScriptValue, CommandArgument, CommandArgs};
+
+/// Below this many letters, there isn't enough text to tell languages apart reliably.
+const MIN_DETECTION_LETTERS: usize = 20;
+
+/// Two-letter ISO 639-1 codes, paired with the ISO 639-3 codes whatlang uses for the same languages.
+const ISO_639_CODES: [(&str, &str); 69] = [
+    ("af", "afr"), ("ak", "aka"), ("am", "amh"), ("ar", "ara"), ("az", "aze"), ("be", "bel"), ("bg", "bul"),
+    ("bn", "ben"), ("ca", "cat"), ("cs", "ces"), ("da", "dan"), ("de", "deu"), ("el", "ell"), ("en", "eng"),
+    ("eo", "epo"), ("es", "spa"), ("et", "est"), ("fa", "pes"), ("fi", "fin"), ("fr", "fra"), ("gu", "guj"),
+    ("he", "heb"), ("hi", "hin"), ("hr", "hrv"), ("hu", "hun"), ("hy", "hye"), ("id", "ind"), ("it", "ita"),
+    ("ja", "jpn"), ("jv", "jav"), ("ka", "kat"), ("km", "khm"), ("kn", "kan"), ("ko", "kor"), ("la", "lat"),
+    ("lt", "lit"), ("lv", "lav"), ("mk", "mkd"), ("ml", "mal"), ("mr", "mar"), ("my", "mya"), ("nb", "nob"),
+    ("ne", "nep"), ("nl", "nld"), ("or", "ori"), ("pa", "pan"), ("pl", "pol"), ("pt", "por"), ("ro", "ron"),
+    ("ru", "rus"), ("si", "sin"), ("sk", "slk"), ("sl", "slv"), ("sn", "sna"), ("sr", "srp"), ("sv", "swe"),
+    ("ta", "tam"), ("te", "tel"), ("th", "tha"), ("tk", "tuk"), ("tl", "tgl"), ("tr", "tur"), ("uk", "ukr"),
+    ("ur", "urd"), ("uz", "uzb"), ("vi", "vie"), ("yi", "yid"), ("zh", "cmn"), ("zu", "zul")
+];
+
+/// The language with a two-letter ISO 639-1 code, like "fr".
+pub fn lang_from_iso_639_1(code: &str) -> Option<Lang> {
+    let code = code.trim().to_ascii_lowercase();
+    let (_, iso_639_3) = ISO_639_CODES.iter().find(|(iso_639_1, _)| *iso_639_1 == code)?;
+    Lang::from_code(iso_639_3)
+}
+
+/// The two-letter ISO 639-1 code of a language, like "fr".
+pub fn iso_639_1_code(lang: Lang) -> Option<&'static str> {
+    ISO_639_CODES.iter()
+        .find(|(_, iso_639_3)| *iso_639_3 == lang.code())
+        .map(|(iso_639_1, _)| *iso_639_1)
+}
+
+/// Detects the language of a text, with a confidence that is lowered for texts too short to be sure about.
+pub fn detect_text_language(text: &str) -> Option<(Lang, f64)> {
+    let info = whatlang::detect(text)?;
+
+    let letters = text.chars().filter(|el| el.is_alphabetic()).count();
+    let mut confidence = info.confidence();
+    if letters < MIN_DETECTION_LETTERS {
+        confidence *= letters as f64 / MIN_DETECTION_LETTERS as f64;
+    }
+
+    Some((info.lang(), confidence))
+}
+
+pub async fn detect_language(_ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("detect_language", &[ "text" ], &args);
+    let text = args.require_string("text")?;
+
+    let (lang, confidence) = match detect_text_language(&text) {
+        Some(detected) => detected,
+        None => return Ok(ScriptValue::Dict(HashMap::from_iter([
+            ("language".to_string(), ScriptValue::None),
+            ("confidence".to_string(), ScriptValue::Float(0.))
+        ])))
+    };
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("language".to_string(), lang.code().to_string().into()),
+        ("name".to_string(), lang.eng_name().to_string().into()),
+        ("confidence".to_string(), ScriptValue::Float(confidence))
+    ])))
+}
+
+pub struct DetectLanguageImpl;
+
+#[async_trait]
+impl CommandImpl for DetectLanguageImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        detect_language(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub fn create_text() -> Plugin {
+    Plugin {
+        name: "Text".to_string(),
+        dependencies: vec![],
+        default_timeout: None,
+        cycle: Box::new(EmptyCycle),
+        commands: vec![
+            Command {
+                name: "detect_language".to_string(),
+                purpose: "Detect which language a text is written in.".to_string(),
+                args: vec![
+                    CommandArgument::new("text", "The text to detect the language of.", "String")
+                ],
+                return_type: "{ language: String | None, name: String, confidence: Float }".to_string(),
+                run: Box::new(DetectLanguageImpl)
+            }
+        ]
+    }
+}
@@ -0,0 +1,194 @@
+use std::{error::Error, fmt::Display, time::Duration, collections::HashMap};
+
+use async_trait::async_trait;
+
+use crate::{Plugin, Command, CommandContext, CommandImpl, EmptyCycle, ScriptValue, CommandArgument, CommandArgs, BrowseRequest, BinaryBody, VisionRequest, invoke, google_items};
+
+/// The image formats that OpenAI's vision models accept.
+const SUPPORTED_IMAGE_TYPES: [&str; 4] = [ "image/png", "image/jpeg", "image/gif", "image/webp" ];
+
+const OCR_PROMPT: &str = "Transcribe all of the text in this image exactly as written, keeping its line breaks. Respond with only the text. If there is no text, respond with \"NO TEXT\".";
+
+const SEARCH_IMAGE_PROMPT: &str = "Describe this image in a few sentences, naming any people, places, products, or text you recognize. Then, on its own last line, write \"QUERY:\" followed by a short web search query for finding out more about what the image shows.";
+
+#[derive(Debug, Clone)]
+pub struct UnsupportedImageError(pub String, pub Option<String>);
+
+impl Display for UnsupportedImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "the file at '{}' is {}, which is not a supported image. Use a PNG, JPEG, GIF, or WebP image.",
+            self.0, self.1.as_deref().unwrap_or("of an unknown type")
+        )
+    }
+}
+
+impl Error for UnsupportedImageError {}
+
+/// Downloads an image through the Browse plugin, so its size limit applies, and turns it into a `data:` URL.
+pub async fn fetch_image(ctx: &mut CommandContext, url: &str) -> Result<String, Box<dyn Error>> {
+    let browse_info = ctx.plugin_data.get_data("Browse")?;
+    let BinaryBody { content_type, data } = invoke::<BinaryBody>(browse_info, "browse binary", BrowseRequest {
+        url: url.to_string(),
+        params: vec![],
+        basic_auth: None
+    }).await?;
+
+    match content_type {
+        Some(content_type) if SUPPORTED_IMAGE_TYPES.contains(&content_type.as_str()) => {
+            Ok(format!("data:{content_type};base64,{data}"))
+        }
+        content_type => Err(Box::new(UnsupportedImageError(url.to_string(), content_type)))
+    }
+}
+
+pub async fn ocr_image(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("ocr_image", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    let image_url = fetch_image(ctx, &url).await?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let text = invoke::<String>(chatgpt_info, "respond vision", VisionRequest {
+        image_url,
+        prompt: OCR_PROMPT.to_string()
+    }).await?;
+
+    let text = text.trim();
+    if text == "NO TEXT" {
+        Ok(ScriptValue::None)
+    } else {
+        Ok(text.to_string().into())
+    }
+}
+
+pub async fn describe_image(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("describe_image", &[ "url", "question" ], &args);
+    let url = args.require_string("url")?;
+    let question = args.optional_string("question")?
+        .unwrap_or_else(|| "Describe this image in detail.".to_string());
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let answer = invoke::<String>(chatgpt_info, "respond vision", VisionRequest {
+        image_url: url,
+        prompt: question
+    }).await?;
+
+    Ok(answer.trim().to_string().into())
+}
+
+/// Splits the vision model's answer into the description and the search query on its last line.
+/// Without a query line, the description's first sentence is searched for.
+fn split_description_query(answer: &str) -> (String, String) {
+    let answer = answer.trim();
+    if let Some((description, query)) = answer.rsplit_once("QUERY:") {
+        let query = query.trim().trim_matches('"').trim();
+        if !query.is_empty() {
+            return (description.trim().to_string(), query.to_string());
+        }
+    }
+
+    let first_sentence = answer.split(|el| el == '.' || el == '\n').next().unwrap_or(answer).trim();
+    (answer.to_string(), first_sentence.to_string())
+}
+
+pub async fn search_image(ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+    let args = CommandArgs::new("search_image", &[ "url" ], &args);
+    let url = args.require_string("url")?;
+
+    let chatgpt_info = ctx.plugin_data.get_data("ChatGPT")?;
+    let answer = invoke::<String>(chatgpt_info, "respond vision", VisionRequest {
+        image_url: url,
+        prompt: SEARCH_IMAGE_PROMPT.to_string()
+    }).await?;
+    let (description, query) = split_description_query(&answer);
+
+    let results = match google_items(ctx, &query, None).await? {
+        Ok(items) => items,
+        Err(failure) => failure.to_value(&query)
+    };
+
+    Ok(ScriptValue::Dict(HashMap::from_iter([
+        ("description".to_string(), description.into()),
+        ("query".to_string(), query.into()),
+        ("results".to_string(), results)
+    ])))
+}
+
+pub struct OcrImageImpl;
+
+#[async_trait]
+impl CommandImpl for OcrImageImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        ocr_image(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct DescribeImageImpl;
+
+#[async_trait]
+impl CommandImpl for DescribeImageImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        describe_image(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub struct SearchImageImpl;
+
+#[async_trait]
+impl CommandImpl for SearchImageImpl {
+    async fn invoke(&self, ctx: &mut CommandContext, args: Vec<ScriptValue>) -> Result<ScriptValue, Box<dyn Error>> {
+        search_image(ctx, args).await
+    }
+
+    fn box_clone(&self) -> Box<dyn CommandImpl> {
+        Box::new(Self)
+    }
+}
+
+pub fn create_vision() -> Plugin {
+    Plugin {
+        name: "Vision".to_string(),
+        dependencies: vec![ "ChatGPT".to_string(), "Browse".to_string() ],
+        default_timeout: Some(Duration::from_secs(120)),
+        cycle: Box::new(EmptyCycle),
+        commands: vec![
+            Command {
+                name: "ocr_image".to_string(),
+                purpose: "Read the text in an image.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the image.", "String")
+                ],
+                return_type: "String | None".to_string(),
+                run: Box::new(OcrImageImpl)
+            },
+            Command {
+                name: "describe_image".to_string(),
+                purpose: "Ask a question about an image, or get a description of it.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the image.", "String"),
+                    CommandArgument::new("question", "What to ask about the image. Leave out for a description.", "String | None")
+                ],
+                return_type: "String".to_string(),
+                run: Box::new(DescribeImageImpl)
+            },
+            Command {
+                name: "search_image".to_string(),
+                purpose: "Describe an image, then search Google for what it shows. Needs the Google plugin.".to_string(),
+                args: vec![
+                    CommandArgument::new("url", "The URL of the image.", "String")
+                ],
+                return_type: "{ description: String, query: String, results: { items: { title: String, link: String, snippet: String }[], citations: { title: String, url: String, fetched_at: String }[] } | { error: String } }".to_string(),
+                run: Box::new(SearchImageImpl)
+            }
+        ]
+    }
+}
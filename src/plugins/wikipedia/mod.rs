@@ -34,7 +34,8 @@ pub async fn search_wikipedia(ctx: &mut CommandContext, query: &str) -> Result<S
         url: "https://en.wikipedia.org/w/api.php".to_string(),
         params: params.iter()
             .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>(),
+        basic_auth: None
     }).await?; 
 
     Ok(json.clone())
@@ -55,7 +56,8 @@ pub async fn get_wikipedia(ctx: &mut CommandContext, name: &str) -> Result<Strin
         url: "https://en.wikipedia.org/w/api.php".to_string(),
         params: params.iter()
             .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>(),
+        basic_auth: None
     }).await?; 
     let json: WikipediaResponse = serde_json::from_str(&json)?;
     let page = json.query.pages.iter().next().ok_or(WikipediaNoPageError)?.1;
@@ -100,6 +102,10 @@ impl CommandImpl for WikipediaSearchImpl {
     fn box_clone(&self) -> Box<dyn CommandImpl> {
         Box::new(Self)
     }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
 }
 
 pub struct WikipediaBrowseImpl;
@@ -113,12 +119,17 @@ impl CommandImpl for WikipediaBrowseImpl {
     fn box_clone(&self) -> Box<dyn CommandImpl> {
         Box::new(Self)
     }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
 }
 
 pub fn create_wikipedia() -> Plugin {
     Plugin {
         name: "Wikipedia".to_string(),
         dependencies: vec![ "Browse".to_string() ],
+        default_timeout: None,
         cycle: Box::new(EmptyCycle),
         commands: vec![
             Command {
@@ -6,7 +6,7 @@ use select::{document::Document, predicate::Name};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, BrowseRequest, invoke, PluginData, PluginCycle, PluginDataNoInvoke, ScriptValue, CommandArgument};
+use crate::{CommandContext, CommandImpl, Plugin, EmptyCycle, Command, BrowseRequest, invoke, PluginData, PluginCycle, PluginDataNoInvoke, ScriptValue, CommandArgument, check_config};
 
 #[derive(Debug, Clone)]
 pub struct WolframNoQueryError;
@@ -50,7 +50,8 @@ pub async fn ask_wolfram(ctx: &mut CommandContext, query: &str) -> Result<String
         url: "http://api.wolframalpha.com/v2/query".to_string(),
         params: params.iter()
             .map(|el| (el.0.to_string(), el.1.to_string()))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>(),
+        basic_auth: None
     }).await?; 
 
     Ok(extract_text_from_wolfram(&xml))
@@ -103,9 +104,13 @@ impl PluginCycle for WolframCycle {
         Ok(None)
     }
 
-    fn create_data(&self, value: Value) -> Option<Box<dyn PluginData>> {
-        let data: WolframData = serde_json::from_value(value).ok()?;
-        Some(Box::new(data))
+    fn create_data(&self, value: Value) -> Result<Option<Box<dyn PluginData>>, Box<dyn Error>> {
+        let data: WolframData = serde_json::from_value(value)?;
+        Ok(Some(Box::new(data)))
+    }
+
+    fn validate_config(&self, value: Value) -> Result<(), Box<dyn Error>> {
+        check_config::<WolframData>(value)
     }
 }
 
@@ -113,6 +118,7 @@ pub fn create_wolfram() -> Plugin {
     Plugin {
         name: "Wolfram".to_string(),
         dependencies: vec![ "Browse".to_string() ],
+        default_timeout: None,
         cycle: Box::new(WolframCycle),
         commands: vec![
             Command {
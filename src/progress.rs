@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+use chrono::Utc;
+use colored::Colorize;
+use serde::{Serialize, Deserialize};
+
+use crate::CommandContext;
+
+/// One step of a long-running command, for showing progress while it runs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProgressEvent {
+    pub command: String,
+    pub stage: String,
+    pub step: Option<usize>,
+    pub total: Option<usize>,
+    pub at: String
+}
+
+pub type ProgressListener = Box<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// How many of the most recent events are kept, so that a long session doesn't keep every one.
+pub const MAX_PROGRESS_EVENTS: usize = 100;
+
+#[derive(Default)]
+pub struct ProgressSink {
+    pub events: VecDeque<ProgressEvent>,
+    pub listeners: Vec<ProgressListener>
+}
+
+impl ProgressSink {
+    pub fn emit(&mut self, event: ProgressEvent) {
+        for listener in &self.listeners {
+            listener(&event);
+        }
+        if self.events.len() >= MAX_PROGRESS_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Reports a stage of a command, like "fetching", or a numbered step like chunk 2 of 5.
+pub fn report_progress(ctx: &mut CommandContext, command: &str, stage: &str, step: Option<(usize, usize)>) {
+    match step {
+        Some((step, total)) => println!("{} {} / {}", stage.green(), step, total),
+        None => println!("{}", stage.green())
+    }
+
+    ctx.progress.emit(ProgressEvent {
+        command: command.to_string(),
+        stage: stage.to_string(),
+        step: step.map(|el| el.0),
+        total: step.map(|el| el.1),
+        at: Utc::now().to_rfc3339()
+    });
+}
@@ -23,8 +23,12 @@ impl Serialize for ScriptValue {
                 seq.end()
             },
             ScriptValue::Dict(dict) => {
+                // Keys are sorted so that the same dict always serializes the same way, whatever the hash order.
+                let mut entries = dict.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+
                 let mut map = serializer.serialize_map(Some(dict.len()))?;
-                for (key, value) in dict {
+                for (key, value) in entries {
                     map.serialize_entry(key, value)?;
                 }
                 map.end()
@@ -0,0 +1,44 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use regex::{Regex, Captures};
+
+#[derive(Debug, Clone)]
+pub struct MissingTemplateVariablesError(pub Vec<String>);
+
+impl Display for MissingTemplateVariablesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the template uses the variable(s) {}, which were not given.", self.0.join(", "))
+    }
+}
+
+impl Error for MissingTemplateVariablesError {}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{([\w.]+)\}").unwrap()
+}
+
+/// Lists the names of every `{placeholder}` in the template, in order, without duplicates.
+pub fn template_placeholders(template: &str) -> Vec<String> {
+    let mut placeholders: Vec<String> = vec![];
+    for cap in placeholder_regex().captures_iter(template) {
+        let name = cap[1].to_string();
+        if !placeholders.contains(&name) {
+            placeholders.push(name);
+        }
+    }
+    placeholders
+}
+
+/// Replaces every `{placeholder}` in the template with its variable.
+pub fn render_template(template: &str, variables: &HashMap<String, String>) -> Result<String, MissingTemplateVariablesError> {
+    let missing = template_placeholders(template).into_iter()
+        .filter(|el| !variables.contains_key(el))
+        .map(|el| format!("{{{el}}}"))
+        .collect::<Vec<_>>();
+
+    if missing.len() > 0 {
+        return Err(MissingTemplateVariablesError(missing));
+    }
+
+    Ok(placeholder_regex().replace_all(template, |cap: &Captures| variables[&cap[1]].clone()).to_string())
+}